@@ -0,0 +1,515 @@
+use std::{
+    io::Read,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{auth, config, error::Error, ledger, mail, printer, session};
+
+const SETTINGS_VERSION: u32 = 1;
+const SETTINGS_FILE: &str = "admin-api.json";
+/// Tokens shorter than this are too easy to guess from the LAN.
+const MIN_TOKEN_LEN: usize = 16;
+/// Largest request body accepted, which is plenty for a config.
+const MAX_BODY: u64 = 1024 * 1024;
+/// How many of the latest sales `GET /api/sessions` returns.
+const RECENT_SALES: usize = 50;
+
+/// The optional HTTP API attendants use to manage the booth from their
+/// phones. It is plain HTTP, so the token and every response cross the
+/// network unencrypted; it should listen on the booth's address on a network
+/// only staff can join. No response carries a secret, and the config it
+/// serves has no attendant PINs. Changes take effect the next time the booth
+/// starts.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AdminApiSettings {
+    pub enabled: bool,
+    /// The address to listen on, e.g. the booth's address on the staff Wi-Fi.
+    #[serde(default = "default_address")]
+    pub address: IpAddr,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Sent by clients as `Authorization: Bearer <token>`.
+    pub token: String,
+}
+
+fn default_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+fn default_port() -> u16 {
+    8787
+}
+
+/// Body of `PUT /api/paper`, setting the page counter.
+#[derive(Deserialize)]
+struct Paper {
+    printed: u64,
+}
+
+#[tauri::command]
+pub fn save_admin_api_settings(
+    directory: String,
+    settings: AdminApiSettings,
+    token: String,
+) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    if settings.enabled && settings.token.len() < MIN_TOKEN_LEN {
+        return Err(Error::Invalid(format!(
+            "The admin API token must be at least {} characters",
+            MIN_TOKEN_LEN
+        )));
+    }
+    if settings.address.is_unspecified() {
+        return Err(Error::Invalid(
+            "Choose the address of the network attendants use, not every interface".to_string(),
+        ));
+    }
+
+    config::save_versioned(&directory, SETTINGS_FILE, SETTINGS_VERSION, &settings)
+        .map_err(Error::Storage)
+}
+
+#[tauri::command]
+pub fn get_or_init_admin_api_settings(
+    directory: String,
+    defaults: AdminApiSettings,
+    token: String,
+) -> Result<AdminApiSettings, Error> {
+    auth::require(&token)?;
+
+    if let Some(settings) = load_settings(&directory) {
+        return Ok(settings);
+    }
+
+    config::save_versioned(&directory, SETTINGS_FILE, SETTINGS_VERSION, &defaults)
+        .map_err(Error::Storage)?;
+
+    Ok(defaults)
+}
+
+pub fn load_settings(directory: &str) -> Option<AdminApiSettings> {
+    config::load_versioned(directory, SETTINGS_FILE, SETTINGS_VERSION)
+}
+
+/// Starts the admin API when it is enabled, on its configured address.
+pub fn spawn(app: AppHandle) {
+    let Ok(directory) = ledger::document_dir(&app) else {
+        return;
+    };
+    let Some(settings) = load_settings(&directory).filter(|settings| settings.enabled) else {
+        return;
+    };
+
+    if settings.token.len() < MIN_TOKEN_LEN {
+        log::warn!("Admin API not started: its token is too short");
+        return;
+    }
+    if settings.address.is_unspecified() {
+        log::warn!("Admin API not started: it needs a specific address to listen on");
+        return;
+    }
+
+    let address = SocketAddr::new(settings.address, settings.port);
+    let server = match Server::http(address) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to start admin API on {}: {}", address, e);
+            return;
+        }
+    };
+    log::info!("Admin API listening on {}", address);
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            if answer(&directory, &settings.token, request) {
+                changed(&app);
+            }
+        }
+    });
+}
+
+/// Answers one request, returning whether it changed the booth's settings.
+fn answer(directory: &str, token: &str, mut request: Request) -> bool {
+    let method = request.method().clone();
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let result = match request.remote_addr() {
+        Some(addr) if is_local(addr) => {
+            if authorised(&request, token) {
+                route(directory, &method, &path, &mut request)
+            } else {
+                Err((
+                    401,
+                    Error::Unauthorized("Missing or invalid token".to_string()),
+                ))
+            }
+        }
+        _ => Err((
+            403,
+            Error::Unauthorized("The admin API only serves the local network".to_string()),
+        )),
+    };
+
+    let changed = method != Method::Get && result.is_ok();
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err((status, error)) => {
+            log::warn!("Admin API {} {} failed: {}", method, path, error);
+            (status, json!(error))
+        }
+    };
+
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(
+            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                .expect("static header is valid"),
+        );
+
+    if let Err(e) = request.respond(response) {
+        log::warn!("Failed to answer admin API request: {}", e);
+    }
+
+    changed
+}
+
+/// Settings changes go through the same checks as the admin pages' saves.
+fn route(
+    directory: &str,
+    method: &Method,
+    path: &str,
+    request: &mut Request,
+) -> Result<Value, (u16, Error)> {
+    match (method, path) {
+        (Method::Get, "/api/config") => config::load_config(directory)
+            .map(|config| json!(config))
+            .ok_or_else(|| {
+                (
+                    404,
+                    Error::NotFound("The booth has not been set up".to_string()),
+                )
+            }),
+        (Method::Put, "/api/config") => {
+            let config: config::Config = read_json(request)?;
+
+            config::write_config(directory.to_string(), config).map_err(rejected)?;
+
+            Ok(json!({ "saved": true }))
+        }
+        (Method::Put, "/api/printers") => {
+            let printers: config::Printers = read_json(request)?;
+
+            config::write_printers(directory.to_string(), printers).map_err(rejected)?;
+
+            Ok(json!({ "saved": true }))
+        }
+        (Method::Put, "/api/paper") => {
+            let Paper { printed } = read_json(request)?;
+
+            config::write_pages(directory.to_string(), printed).map_err(rejected)?;
+
+            Ok(json!({ "printed": printed }))
+        }
+        (Method::Post, "/api/paper/refill") => {
+            config::write_pages(directory.to_string(), 0).map_err(rejected)?;
+            log::info!("Paper refilled through the admin API");
+
+            Ok(json!({ "printed": 0 }))
+        }
+        (Method::Get, "/api/outbox") => Ok(mail::outbox(directory)),
+        (Method::Get, "/api/sessions") => {
+            let sales = ledger::load(directory);
+
+            Ok(json!({
+                "current": session::get_session(),
+                "sales": &sales[sales.len().saturating_sub(RECENT_SALES)..],
+            }))
+        }
+        (Method::Get, "/api/printers") => Ok(json!(config::load_printers(directory)
+            .profiles
+            .iter()
+            .map(printer::status)
+            .collect::<Vec<_>>())),
+        _ => Err((
+            404,
+            Error::NotFound(format!("No route for {} {}", method, path)),
+        )),
+    }
+}
+
+/// Bad input is the client's mistake; anything else is the booth's.
+fn rejected(error: Error) -> (u16, Error) {
+    let status = if matches!(error, Error::Invalid(_)) {
+        400
+    } else {
+        500
+    };
+
+    (status, error)
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(request: &mut Request) -> Result<T, (u16, Error)> {
+    let mut body = Vec::new();
+
+    request
+        .as_reader()
+        .take(MAX_BODY)
+        .read_to_end(&mut body)
+        .map_err(|e| {
+            (
+                400,
+                Error::Invalid(format!("Failed to read request: {}", e)),
+            )
+        })?;
+
+    serde_json::from_slice(&body).map_err(|e| (400, Error::Invalid(format!("Invalid JSON: {}", e))))
+}
+
+/// Tells the kiosk to reload what the API changed.
+fn changed(app: &AppHandle) {
+    if let Err(e) = app.emit(config::CONFIG_CHANGED, ()) {
+        log::warn!("Failed to announce config change: {}", e);
+    }
+}
+
+fn authorised(request: &Request, token: &str) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|given| auth::constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+/// Loopback, private and link-local addresses.
+fn is_local(addr: &SocketAddr) -> bool {
+    match addr.ip() {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        net::{Ipv6Addr, SocketAddrV4, SocketAddrV6},
+    };
+
+    use reqwest::{Client, Method as HttpMethod};
+
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    /// Serves the API for a fresh booth directory on a local port, returning
+    /// the directory and the base URL.
+    fn start(name: &str) -> (String, String) {
+        let directory = std::env::temp_dir().join(format!("memorabooth-admin-api-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        let directory = directory.to_string_lossy().to_string();
+
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+
+        let served = directory.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                answer(&served, TOKEN, request);
+            }
+        });
+
+        (directory, format!("http://127.0.0.1:{}", port))
+    }
+
+    /// Sends a request with `token`, returning the status and the JSON body.
+    fn send(
+        method: HttpMethod,
+        url: String,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (u16, Value) {
+        tauri::async_runtime::block_on(async {
+            let mut request = Client::new().request(method, url);
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            if let Some(body) = body {
+                request = request.json(&body);
+            }
+
+            let res = request.send().await.unwrap();
+            (res.status().as_u16(), res.json().await.unwrap())
+        })
+    }
+
+    fn config() -> Value {
+        json!({
+            "plans": [],
+            "digital": { "title": "Digital", "price": 100, "enabled": false },
+            "event_name": "Wedding",
+        })
+    }
+
+    #[test]
+    fn requires_the_bearer_token() {
+        let (_, base) = start("token");
+
+        for token in [None, Some("0123456789abcdeX"), Some("")] {
+            let (status, body) = send(HttpMethod::GET, format!("{}/api/outbox", base), token, None);
+
+            assert_eq!(status, 401);
+            assert_eq!(body["code"], "unauthorized");
+        }
+
+        let (status, _) = send(
+            HttpMethod::GET,
+            format!("{}/api/outbox", base),
+            Some(TOKEN),
+            None,
+        );
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn saves_and_serves_the_config() {
+        let (directory, base) = start("config");
+        let url = format!("{}/api/config", base);
+
+        let (status, body) = send(HttpMethod::GET, url.clone(), Some(TOKEN), None);
+        assert_eq!(status, 404);
+        assert_eq!(body["code"], "not_found");
+
+        let (status, _) = send(HttpMethod::PUT, url.clone(), Some(TOKEN), Some(config()));
+        assert_eq!(status, 200);
+        assert_eq!(
+            config::load_config(&directory).unwrap().event_name,
+            "Wedding"
+        );
+
+        let (status, body) = send(HttpMethod::GET, url, Some(TOKEN), None);
+        assert_eq!(status, 200);
+        assert_eq!(body["event_name"], "Wedding");
+    }
+
+    #[test]
+    fn checks_settings_like_the_admin_pages() {
+        let (directory, base) = start("checks");
+
+        let mut branded = config();
+        branded["branding"] = json!("A name far too long to print under the photos");
+        let (status, body) = send(
+            HttpMethod::PUT,
+            format!("{}/api/config", base),
+            Some(TOKEN),
+            Some(branded),
+        );
+        assert_eq!(status, 400);
+        assert_eq!(body["code"], "invalid");
+        assert!(config::load_config(&directory).is_none());
+
+        let (status, body) = send(
+            HttpMethod::PUT,
+            format!("{}/api/printers", base),
+            Some(TOKEN),
+            Some(json!({
+                "active": "Broken",
+                "profiles": [{
+                    "name": "Broken",
+                    "queue": null,
+                    "paper_width": 4.0,
+                    "paper_height": 6.0,
+                    "dpi": 0,
+                    "margin": 0.0,
+                    "bleed": 0.0,
+                    "media": "w288h432",
+                    "cuts_strips": true,
+                    "strip_media": null,
+                }],
+            })),
+        );
+        assert_eq!(status, 400);
+        assert_eq!(body["code"], "invalid");
+
+        let capacity = config::active_printer(&directory).paper_capacity;
+        let (status, _) = send(
+            HttpMethod::PUT,
+            format!("{}/api/paper", base),
+            Some(TOKEN),
+            Some(json!({ "printed": capacity + 1 })),
+        );
+        assert_eq!(status, 400);
+
+        let (status, body) = send(
+            HttpMethod::PUT,
+            format!("{}/api/paper", base),
+            Some(TOKEN),
+            Some(json!({ "printed": capacity })),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body["printed"], capacity);
+        assert_eq!(config::load_pages(&directory), capacity);
+    }
+
+    #[test]
+    fn rejects_bad_json_and_unknown_routes() {
+        let (_, base) = start("routes");
+
+        let (status, body) = send(
+            HttpMethod::PUT,
+            format!("{}/api/config", base),
+            Some(TOKEN),
+            Some(json!({ "plans": "none" })),
+        );
+        assert_eq!(status, 400);
+        assert_eq!(body["code"], "invalid");
+
+        let (status, body) = send(
+            HttpMethod::DELETE,
+            format!("{}/api/config", base),
+            Some(TOKEN),
+            None,
+        );
+        assert_eq!(status, 404);
+        assert_eq!(body["code"], "not_found");
+    }
+
+    #[test]
+    fn only_local_networks_are_local() {
+        let v4 = |a, b, c, d| SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(a, b, c, d), 80));
+        let v6 = |ip: Ipv6Addr| SocketAddr::V6(SocketAddrV6::new(ip, 80, 0, 0));
+
+        assert!(is_local(&v4(127, 0, 0, 1)));
+        assert!(is_local(&v4(192, 168, 1, 20)));
+        assert!(is_local(&v4(10, 0, 0, 5)));
+        assert!(is_local(&v4(172, 16, 0, 1)));
+        assert!(is_local(&v4(169, 254, 3, 4)));
+        assert!(!is_local(&v4(8, 8, 8, 8)));
+        assert!(!is_local(&v4(172, 32, 0, 1)));
+
+        assert!(is_local(&v6(Ipv6Addr::LOCALHOST)));
+        assert!(is_local(&v6("fd12:3456::1".parse().unwrap())));
+        assert!(is_local(&v6("fe80::1".parse().unwrap())));
+        assert!(is_local(&v6("::ffff:192.168.0.2".parse().unwrap())));
+        assert!(!is_local(&v6("::ffff:8.8.8.8".parse().unwrap())));
+        assert!(!is_local(&v6("2001:db8::1".parse().unwrap())));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{fs, fs::File, io::BufWriter, path::Path, time::Instant};
+
+use chrono::Utc;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::FilterType::Triangle,
+    Delay, Frame, RgbaImage,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{camera, error::Error, photos};
+
+const BURST_FRAMES: u32 = 10;
+const BURST_INTERVAL_MS: u32 = 150;
+const FRAME_DELAY_MS: u32 = 100;
+const ANIMATION_WIDTH: u32 = 480;
+// 1 is the best quantization and 30 the fastest; 10 keeps a boomerang under
+// a couple of seconds on the Pi without visible banding.
+const GIF_SPEED: i32 = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AnimationMode {
+    /// The burst played forward on a loop.
+    Gif,
+    /// The burst played forward and then back, so the loop has no jump.
+    Boomerang,
+}
+
+/// Captures a burst and encodes it as an animated GIF in the pictures
+/// directory, returning its path for the digital add-on.
+#[tauri::command(async)]
+pub async fn capture_animation(app: AppHandle, mode: AnimationMode) -> Result<String, Error> {
+    let pictures = app
+        .path()
+        .picture_dir()
+        .map_err(|e| Error::Storage(format!("Failed to find pictures directory: {}", e)))?;
+
+    let burst_dir = std::env::temp_dir();
+    let prefix = format!("memorabooth-burst-{:x}", Utc::now().timestamp_millis());
+
+    let capture_start = Instant::now();
+    let (timeout, settings) = camera::capture_setup(&app);
+    let frames = camera::capture_burst(
+        &burst_dir,
+        &prefix,
+        BURST_FRAMES,
+        BURST_INTERVAL_MS,
+        timeout,
+        &settings,
+    )?;
+    let capture_time = capture_start.elapsed();
+
+    let output_path = pictures.join(format!("{}.gif", prefix.replace("-burst", "")));
+
+    let encode_start = Instant::now();
+    let result = encode(&frames, mode, &output_path);
+    let encode_time = encode_start.elapsed();
+
+    for frame in &frames {
+        if let Err(e) = fs::remove_file(frame) {
+            log::warn!("Failed to delete burst frame {}: {}", frame, e);
+        }
+    }
+    result.map_err(Error::Imaging)?;
+
+    log::info!(
+        "{:?} of {} frames: capture {:?}, encode {:?}",
+        mode,
+        frames.len(),
+        capture_time,
+        encode_time
+    );
+
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Encodes `frames` as a looping GIF at `output_path`.
+pub fn encode(frames: &[String], mode: AnimationMode, output_path: &Path) -> Result<(), String> {
+    let images: Vec<RgbaImage> = frames
+        .par_iter()
+        .map(|path| {
+            // Upright by the frame's EXIF orientation, and decoded no larger
+            // than the animation needs
+            let img = photos::decode(path, Some((ANIMATION_WIDTH, 1)))?;
+            let height = img.height() * ANIMATION_WIDTH / img.width();
+
+            Ok(img
+                .resize_exact(ANIMATION_WIDTH, height, Triangle)
+                .to_rgba8())
+        })
+        .collect::<Result<_, String>>()?;
+
+    let order = frame_order(mode, images.len());
+
+    let file =
+        File::create(output_path).map_err(|e| format!("Failed to create animation: {}", e))?;
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|e| format!("Failed to encode animation: {}", e))?;
+
+    let delay = Delay::from_numer_denom_ms(FRAME_DELAY_MS, 1);
+    encoder
+        .encode_frames(
+            order
+                .into_iter()
+                .map(|i| Frame::from_parts(images[i].clone(), 0, 0, delay)),
+        )
+        .map_err(|e| format!("Failed to encode animation: {}", e))
+}
+
+/// The order `count` frames play in, by index.
+fn frame_order(mode: AnimationMode, count: usize) -> Vec<usize> {
+    match mode {
+        AnimationMode::Gif => (0..count).collect(),
+        // The way back skips both ends so neither is shown twice in a row
+        AnimationMode::Boomerang => (0..count)
+            .chain((1..count.saturating_sub(1)).rev())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gifs_play_forward() {
+        assert_eq!(frame_order(AnimationMode::Gif, 4), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn boomerangs_play_back_without_repeating_the_ends() {
+        assert_eq!(
+            frame_order(AnimationMode::Boomerang, 5),
+            [0, 1, 2, 3, 4, 3, 2, 1]
+        );
+        // Looping from the last frame straight to the first loses nothing
+        assert_eq!(frame_order(AnimationMode::Boomerang, 3), [0, 1, 2, 1]);
+        assert_eq!(frame_order(AnimationMode::Boomerang, 2), [0, 1]);
+        assert_eq!(frame_order(AnimationMode::Boomerang, 1), [0]);
+        assert!(frame_order(AnimationMode::Boomerang, 0).is_empty());
+    }
+
+    #[test]
+    fn encodes_a_boomerang_of_every_frame() {
+        let directory = std::env::temp_dir().join("memorabooth-animation");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let frames: Vec<String> = (0..3u8)
+            .map(|i| {
+                let path = directory.join(format!("frame-{}.png", i));
+                RgbaImage::from_pixel(64, 36, image::Rgba([i * 100, 0, 0, 255]))
+                    .save(&path)
+                    .unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let output = directory.join("boomerang.gif");
+
+        encode(&frames, AnimationMode::Boomerang, &output).unwrap();
+
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::BufReader::new(
+            File::open(&output).unwrap(),
+        ))
+        .unwrap();
+        let reds: Vec<u8> = image::AnimationDecoder::into_frames(decoder)
+            .map(|frame| frame.unwrap().buffer().get_pixel(0, 0)[0])
+            .collect();
+
+        assert_eq!(reds.len(), 4);
+        assert!(reds[0] < reds[1] && reds[1] < reds[2] && reds[3] == reds[1]);
+    }
+}
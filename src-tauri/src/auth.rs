@@ -0,0 +1,424 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
+    Argon2,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{config, error::Error, ledger};
+
+const ADMIN_VERSION: u32 = 1;
+const ADMIN_FILE: &str = "admin.json";
+/// The PIN booths ship with. It only opens a session for choosing a new PIN.
+const DEFAULT_PIN: &str = "5412";
+/// Digits in admin and attendant PINs, as many as the numpad takes.
+pub const PIN_LENGTH: usize = 4;
+/// A session ends after this long without an admin command.
+const SESSION_IDLE: Duration = Duration::from_secs(10 * 60);
+/// Wrong PINs allowed before the numpad locks.
+const FREE_ATTEMPTS: u32 = 3;
+/// The first lockout; each further wrong PIN doubles it, up to `MAX_LOCKOUT`.
+const LOCKOUT: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+static ATTEMPTS: Lazy<Mutex<Attempts>> = Lazy::new(|| Mutex::new(Attempts::default()));
+
+#[derive(Serialize, Deserialize)]
+struct Admin {
+    /// Argon2 PHC string of the admin PIN.
+    pin_hash: String,
+}
+
+struct Session {
+    token: String,
+    expires: Instant,
+    /// Signed in with the default PIN, so only a PIN change is allowed.
+    must_change_pin: bool,
+}
+
+/// Wrong PINs entered on one numpad.
+#[derive(Default)]
+pub struct Attempts {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Serialize)]
+pub struct AdminLogin {
+    pub token: String,
+    pub must_change_pin: bool,
+}
+
+/// Checks the admin PIN and starts an admin session, returning the token
+/// admin-only commands need. A booth still on the default PIN gets a session
+/// that can only change it.
+#[tauri::command(async)]
+pub async fn admin_login(app: AppHandle, pin: String) -> Result<AdminLogin, Error> {
+    let directory = ledger::document_dir(&app).map_err(Error::Storage)?;
+
+    tauri::async_runtime::spawn_blocking(move || login(&directory, &pin))
+        .await
+        .map_err(|e| Error::Internal(format!("PIN check failed: {}", e)))?
+}
+
+fn login(directory: &str, pin: &str) -> Result<AdminLogin, Error> {
+    guarded(&ATTEMPTS, "admin", || verify_pin(directory, pin))?;
+
+    let must_change_pin = !has_pin(directory);
+    let token = new_token();
+    *SESSION.lock().unwrap() = Some(Session {
+        token: token.clone(),
+        expires: Instant::now() + SESSION_IDLE,
+        must_change_pin,
+    });
+    log::info!("Admin signed in");
+
+    Ok(AdminLogin {
+        token,
+        must_change_pin,
+    })
+}
+
+#[tauri::command]
+pub fn admin_logout(token: String) {
+    let mut session = SESSION.lock().unwrap();
+
+    if session
+        .as_ref()
+        .is_some_and(|session| constant_time_eq(session.token.as_bytes(), token.as_bytes()))
+    {
+        *session = None;
+    }
+}
+
+#[tauri::command(async)]
+pub async fn change_admin_pin(
+    app: AppHandle,
+    token: String,
+    current: String,
+    pin: String,
+) -> Result<(), Error> {
+    check_session(&token, true)?;
+    check_new_pin(&pin)?;
+
+    let directory = ledger::document_dir(&app).map_err(Error::Storage)?;
+
+    tauri::async_runtime::spawn_blocking(move || change_pin(&directory, &current, &pin))
+        .await
+        .map_err(|e| Error::Internal(format!("PIN change failed: {}", e)))?
+}
+
+fn check_new_pin(pin: &str) -> Result<(), Error> {
+    if pin == DEFAULT_PIN {
+        return Err(Error::Invalid(
+            "Choose a PIN other than the default".to_string(),
+        ));
+    }
+    if !is_valid_pin(pin) {
+        return Err(Error::Invalid(format!(
+            "The PIN must be {} digits",
+            PIN_LENGTH
+        )));
+    }
+
+    Ok(())
+}
+
+/// Whether `pin` can be typed on the numpad.
+pub fn is_valid_pin(pin: &str) -> bool {
+    pin.len() == PIN_LENGTH && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Replaces the admin PIN once `current` checks out, which also lifts the
+/// session's PIN change requirement.
+fn change_pin(directory: &str, current: &str, pin: &str) -> Result<(), Error> {
+    guarded(&ATTEMPTS, "admin", || verify_pin(directory, current))?;
+    save_pin(directory, pin)?;
+
+    if let Some(session) = SESSION.lock().unwrap().as_mut() {
+        session.must_change_pin = false;
+    }
+    log::info!("Admin PIN changed");
+
+    Ok(())
+}
+
+/// Fails unless `token` belongs to the current admin session, and keeps that
+/// session alive when it does.
+pub fn require(token: &str) -> Result<(), Error> {
+    check_session(token, false)
+}
+
+fn check_session(token: &str, changing_pin: bool) -> Result<(), Error> {
+    let mut guard = SESSION.lock().unwrap();
+    let now = Instant::now();
+
+    match guard.as_mut() {
+        Some(session) if session.expires <= now => {
+            *guard = None;
+            Err(Error::Unauthorized(
+                "The admin session has expired".to_string(),
+            ))
+        }
+        Some(session) if constant_time_eq(session.token.as_bytes(), token.as_bytes()) => {
+            session.expires = now + SESSION_IDLE;
+
+            if session.must_change_pin && !changing_pin {
+                return Err(Error::Unauthorized(
+                    "Change the default admin PIN first".to_string(),
+                ));
+            }
+            Ok(())
+        }
+        _ => Err(Error::Unauthorized("Sign in as an admin first".to_string())),
+    }
+}
+
+/// Compares without stopping at the first difference, so response times
+/// don't give a secret away.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Checks a PIN with `verify`, counting the attempt against `attempts`
+/// before it runs so concurrent guesses can't get past the limit.
+pub fn guarded(
+    attempts: &Mutex<Attempts>,
+    what: &str,
+    verify: impl FnOnce() -> Result<bool, Error>,
+) -> Result<(), Error> {
+    let lockout = reserve(attempts)?;
+
+    if verify()? {
+        *attempts.lock().unwrap() = Attempts::default();
+        return Ok(());
+    }
+    log::warn!("Wrong {} PIN entered", what);
+
+    Err(Error::Unauthorized(match lockout {
+        Some(lockout) => format!("Too many wrong PINs, try again in {}s", lockout.as_secs()),
+        None => "Wrong PIN".to_string(),
+    }))
+}
+
+/// Counts an attempt as wrong until it is proven right, returning how long
+/// the numpad locks for if it is, or failing while it is locked.
+fn reserve(attempts: &Mutex<Attempts>) -> Result<Option<Duration>, Error> {
+    let mut attempts = attempts.lock().unwrap();
+    let now = Instant::now();
+
+    if let Some(until) = attempts.locked_until.filter(|until| *until > now) {
+        return Err(Error::Unauthorized(format!(
+            "Too many wrong PINs, try again in {}s",
+            (until - now).as_secs() + 1
+        )));
+    }
+    attempts.failures += 1;
+
+    let Some(over) = attempts.failures.checked_sub(FREE_ATTEMPTS) else {
+        return Ok(None);
+    };
+    let lockout = LOCKOUT
+        .checked_mul(1 << over.min(16))
+        .unwrap_or(MAX_LOCKOUT)
+        .min(MAX_LOCKOUT);
+    attempts.locked_until = Some(now + lockout);
+
+    Ok(Some(lockout))
+}
+
+fn load_admin(directory: &str) -> Option<Admin> {
+    config::load_versioned(directory, ADMIN_FILE, ADMIN_VERSION)
+}
+
+fn has_pin(directory: &str) -> bool {
+    load_admin(directory).is_some()
+}
+
+/// Checks `pin` against the admin PIN, or the default one until it is changed.
+fn verify_pin(directory: &str, pin: &str) -> Result<bool, Error> {
+    match load_admin(directory) {
+        Some(admin) => verify_hash(&admin.pin_hash, pin),
+        None => Ok(constant_time_eq(pin.as_bytes(), DEFAULT_PIN.as_bytes())),
+    }
+}
+
+/// Checks `pin` against an Argon2 PHC string.
+pub fn verify_hash(pin_hash: &str, pin: &str) -> Result<bool, Error> {
+    let hash = PasswordHash::new(pin_hash)
+        .map_err(|e| Error::Storage(format!("Invalid PIN hash: {}", e)))?;
+
+    Ok(Argon2::default()
+        .verify_password(pin.as_bytes(), &hash)
+        .is_ok())
+}
+
+/// Hashes `pin` into an Argon2 PHC string with a fresh salt.
+pub fn hash_pin(pin: &str) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Ok(Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map_err(|e| Error::Internal(format!("Failed to hash PIN: {}", e)))?
+        .to_string())
+}
+
+fn save_pin(directory: &str, pin: &str) -> Result<(), Error> {
+    let admin = Admin {
+        pin_hash: hash_pin(pin)?,
+    };
+
+    config::save_versioned(directory, ADMIN_FILE, ADMIN_VERSION, &admin).map_err(Error::Storage)
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serialises the tests that sign in, since there is one admin session.
+    static SIGN_IN: Mutex<()> = Mutex::new(());
+
+    fn booth(name: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("memorabooth-auth-{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        directory.to_string_lossy().to_string()
+    }
+
+    fn unauthorized(result: Result<(), Error>) -> String {
+        match result {
+            Err(Error::Unauthorized(message)) => message,
+            other => panic!("expected unauthorized, got {:?}", other.err()),
+        }
+    }
+
+    /// Lets the current lockout run out without waiting for it.
+    fn expire_lockout(attempts: &Mutex<Attempts>) {
+        let mut attempts = attempts.lock().unwrap();
+        attempts.locked_until = attempts.locked_until.map(|_| Instant::now());
+    }
+
+    #[test]
+    fn lockouts_double_up_to_the_cap() {
+        let attempts = Mutex::new(Attempts::default());
+
+        for _ in 1..FREE_ATTEMPTS {
+            assert_eq!(
+                unauthorized(guarded(&attempts, "test", || Ok(false))),
+                "Wrong PIN"
+            );
+        }
+
+        for lockout in [30, 60, 120, 240, 480, 900, 900] {
+            assert_eq!(
+                unauthorized(guarded(&attempts, "test", || Ok(false))),
+                format!("Too many wrong PINs, try again in {}s", lockout)
+            );
+
+            // Even the right PIN is refused until the lockout ends
+            let locked = unauthorized(guarded(&attempts, "test", || Ok(true)));
+            assert!(locked.starts_with("Too many wrong PINs"));
+
+            expire_lockout(&attempts);
+        }
+    }
+
+    #[test]
+    fn the_right_pin_resets_the_count() {
+        let attempts = Mutex::new(Attempts::default());
+
+        for _ in 0..FREE_ATTEMPTS {
+            let _ = guarded(&attempts, "test", || Ok(false));
+        }
+        expire_lockout(&attempts);
+        guarded(&attempts, "test", || Ok(true)).unwrap();
+
+        // The next wrong PINs are free again
+        for _ in 1..FREE_ATTEMPTS {
+            assert_eq!(
+                unauthorized(guarded(&attempts, "test", || Ok(false))),
+                "Wrong PIN"
+            );
+        }
+    }
+
+    #[test]
+    fn compares_in_constant_time() {
+        assert!(constant_time_eq(b"5412", b"5412"));
+        assert!(!constant_time_eq(b"5412", b"5413"));
+        assert!(!constant_time_eq(b"5412", b"54120"));
+        assert!(!constant_time_eq(b"", b"5412"));
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let _lock = SIGN_IN.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = booth("idle");
+        save_pin(&directory, "2468").unwrap();
+
+        let session = login(&directory, "2468").unwrap();
+        assert!(!session.must_change_pin);
+        require(&session.token).unwrap();
+        assert_eq!(
+            unauthorized(require("not the token")),
+            "Sign in as an admin first"
+        );
+
+        SESSION.lock().unwrap().as_mut().unwrap().expires = Instant::now();
+        assert_eq!(
+            unauthorized(require(&session.token)),
+            "The admin session has expired"
+        );
+        // The expired session is gone rather than revived by the next call
+        assert!(SESSION.lock().unwrap().is_none());
+        assert_eq!(
+            unauthorized(require(&session.token)),
+            "Sign in as an admin first"
+        );
+    }
+
+    #[test]
+    fn the_default_pin_only_allows_a_pin_change() {
+        let _lock = SIGN_IN.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = booth("default-pin");
+
+        let session = login(&directory, DEFAULT_PIN).unwrap();
+        assert!(session.must_change_pin);
+        assert_eq!(
+            unauthorized(require(&session.token)),
+            "Change the default admin PIN first"
+        );
+        check_session(&session.token, true).unwrap();
+
+        assert!(matches!(check_new_pin(DEFAULT_PIN), Err(Error::Invalid(_))));
+        assert!(matches!(check_new_pin("12345"), Err(Error::Invalid(_))));
+        assert!(matches!(check_new_pin("12a4"), Err(Error::Invalid(_))));
+
+        change_pin(&directory, DEFAULT_PIN, "2468").unwrap();
+        require(&session.token).unwrap();
+
+        // The default PIN stops working once a PIN is set
+        assert_eq!(
+            unauthorized(login(&directory, DEFAULT_PIN).map(|_| ())),
+            "Wrong PIN"
+        );
+        assert!(!login(&directory, "2468").unwrap().must_change_pin);
+    }
+}
@@ -0,0 +1,645 @@
+use std::{
+    fmt, fs,
+    io::{ErrorKind, Read},
+    path::Path,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    config::{self, CaptureSettings},
+    error::Error,
+    exposure::{self, ExposureStats},
+    logging, photos, preview,
+};
+
+/// The part of the sensor framed for both the live preview and the capture.
+pub const CAPTURE_ROI: &str = "0.075,0.15,0.79,0.85";
+const CAPTURE_ATTEMPTS: u32 = 2;
+// Gives the preview or a previous capture time to release the camera
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static TEST_FRAME_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug)]
+pub enum CaptureError {
+    /// Another process still holds the camera.
+    Busy,
+    NotDetected,
+    Timeout(Duration),
+    Failed(String),
+    /// The camera reported success but the photo is missing or unreadable.
+    InvalidOutput(String),
+}
+
+impl CaptureError {
+    /// Whether a second attempt has any chance; a missing camera will not
+    /// come back by itself.
+    fn is_retryable(&self) -> bool {
+        !matches!(self, CaptureError::NotDetected)
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Busy => write!(f, "Camera is busy"),
+            CaptureError::NotDetected => write!(f, "No camera detected"),
+            CaptureError::Timeout(timeout) => {
+                write!(f, "Camera did not respond within {}s", timeout.as_secs())
+            }
+            CaptureError::Failed(message) => write!(f, "Capture failed: {}", message),
+            CaptureError::InvalidOutput(message) => {
+                write!(f, "Capture produced no usable photo: {}", message)
+            }
+        }
+    }
+}
+
+/// A sensor mode the camera can stream at.
+#[derive(Serialize, Clone, Debug)]
+pub struct CameraMode {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CameraInfo {
+    pub index: u32,
+    pub model: String,
+    /// Full sensor resolution.
+    pub width: u32,
+    pub height: u32,
+    /// Bit depth and Bayer order, e.g. `10-bit RGGB`.
+    pub format: String,
+    pub device: String,
+    pub modes: Vec<CameraMode>,
+}
+
+/// A throwaway frame taken to check the camera and its exposure.
+#[derive(Serialize, Debug)]
+pub struct TestFrame {
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub exposure: ExposureStats,
+    pub capture_ms: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SelfTestReport {
+    pub camera: Option<CameraInfo>,
+    #[serde(flatten)]
+    pub frame: TestFrame,
+}
+
+#[tauri::command(async)]
+pub async fn list_cameras() -> Result<Vec<CameraInfo>, Error> {
+    tauri::async_runtime::spawn_blocking(cameras)
+        .await
+        .map_err(|e| Error::Internal(format!("Camera listing failed: {}", e)))?
+        .map_err(Error::from)
+}
+
+/// Takes a throwaway frame and reports its exposure, so attendants can tell
+/// the camera works and is pointed at a lit scene.
+#[tauri::command(async)]
+pub async fn camera_self_test(app: AppHandle) -> Result<SelfTestReport, Error> {
+    let (timeout, settings) = capture_setup(&app);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let camera = cameras()?.into_iter().next();
+        let frame = test_frame(timeout, &settings)?;
+
+        Ok(SelfTestReport { camera, frame })
+    })
+    .await
+    .map_err(|e| Error::Internal(format!("Camera self-test failed: {}", e)))?
+}
+
+/// Takes a frame to a scratch file with `settings`, measures it and deletes
+/// it again.
+pub fn test_frame(timeout: Duration, settings: &CaptureSettings) -> Result<TestFrame, Error> {
+    // Self-tests and health checks may overlap, so each gets its own file
+    let path = std::env::temp_dir().join(format!(
+        "memorabooth-test-frame-{}-{}.jpg",
+        std::process::id(),
+        TEST_FRAME_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let path = path.to_string_lossy();
+
+    let capture_start = Instant::now();
+    let captured = capture_still(&path, timeout, settings);
+    let capture_ms = capture_start.elapsed().as_millis() as u64;
+
+    let frame = captured
+        .map_err(Error::from)
+        .and_then(|()| photos::decode(&path, None).map_err(Error::Camera));
+
+    match fs::remove_file(path.as_ref()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Failed to delete test frame: {}", e),
+    }
+
+    let frame = frame?;
+    let exposure = exposure::measure(&frame);
+
+    Ok(TestFrame {
+        width: frame.width(),
+        height: frame.height(),
+        exposure,
+        capture_ms,
+    })
+}
+
+/// Every camera libcamera can see. An empty list means none is connected.
+pub fn cameras() -> Result<Vec<CameraInfo>, CaptureError> {
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("libcamera-still")
+            .arg("--list-cameras")
+            .output()
+            .map_err(|e| {
+                CaptureError::Failed(format!("Failed to execute camera command: {}", e))
+            })?;
+
+        // libcamera prints the list to stdout or stderr depending on version
+        let listing = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(parse_camera_list(&listing))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let (width, height) = image::image_dimensions("sample.jpg")
+            .map_err(|e| CaptureError::Failed(format!("Failed to read sample image: {}", e)))?;
+
+        Ok(vec![CameraInfo {
+            index: 0,
+            model: "sample".to_string(),
+            width,
+            height,
+            format: "JPEG".to_string(),
+            device: "sample.jpg".to_string(),
+            modes: vec![],
+        }])
+    }
+}
+
+/// Parses `libcamera-still --list-cameras`, which looks like:
+///
+/// ```text
+/// 0 : imx708 [4608x2592 10-bit RGGB] (/base/soc/i2c0mux/i2c@1/imx708@1a)
+///     Modes: 'SRGGB10_CSI2P' : 1536x864 [120.13 fps - (768, 432)/3072x1728 crop]
+///                              2304x1296 [56.03 fps - (0, 0)/4608x2592 crop]
+/// ```
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_camera_list(listing: &str) -> Vec<CameraInfo> {
+    fn parse_size(size: &str) -> Option<(u32, u32)> {
+        let (width, height) = size.split_once('x')?;
+        Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+    }
+
+    let mut cameras: Vec<CameraInfo> = Vec::new();
+
+    for line in listing.lines() {
+        let line = line.trim();
+
+        if let Some((index, rest)) = line
+            .split_once(" : ")
+            .and_then(|(index, rest)| Some((index.trim().parse::<u32>().ok()?, rest)))
+        {
+            let Some((model, rest)) = rest.split_once(" [") else {
+                continue;
+            };
+            let Some((sensor, device)) = rest.split_once(']') else {
+                continue;
+            };
+            let (size, format) = sensor.split_once(' ').unwrap_or((sensor, ""));
+            let (width, height) = parse_size(size).unwrap_or_default();
+
+            cameras.push(CameraInfo {
+                index,
+                model: model.trim().to_string(),
+                width,
+                height,
+                format: format.trim().to_string(),
+                device: device.trim().trim_matches(['(', ')']).to_string(),
+                modes: vec![],
+            });
+            continue;
+        }
+
+        // Mode lines belong to the camera above them
+        let Some(camera) = cameras.last_mut() else {
+            continue;
+        };
+        let mode = line.rsplit(" : ").next().unwrap_or(line);
+        let Some((size, rest)) = mode.split_once(" [") else {
+            continue;
+        };
+        let Some((width, height)) = parse_size(size) else {
+            continue;
+        };
+        let fps = rest
+            .split_whitespace()
+            .next()
+            .and_then(|fps| fps.parse().ok())
+            .unwrap_or_default();
+
+        camera.modes.push(CameraMode { width, height, fps });
+    }
+
+    cameras
+}
+
+#[tauri::command(async)]
+pub async fn capture(app: AppHandle, output_path: String) -> Result<String, Error> {
+    let started = Instant::now();
+    let (timeout, settings) = capture_setup(&app);
+    let path = output_path.clone();
+
+    let result =
+        tauri::async_runtime::spawn_blocking(move || capture_still(&path, timeout, &settings))
+            .await
+            .map_err(|e| Error::Internal(format!("Capture task failed: {}", e)))
+            .and_then(|captured| captured.map_err(Error::from))
+            .map(|_| output_path);
+    logging::command("capture", started, &result);
+
+    result
+}
+
+/// How long one camera run may take before it is killed, and the exposure
+/// to shoot with, from the stored settings.
+pub fn capture_setup(app: &AppHandle) -> (Duration, CaptureSettings) {
+    let Ok(directory) = app.path().document_dir() else {
+        return (
+            Duration::from_secs(config::default_capture_timeout()),
+            CaptureSettings::default(),
+        );
+    };
+    let directory = directory.to_string_lossy();
+
+    let seconds = config::load_config(&directory)
+        .map(|config| config.capture_timeout)
+        .unwrap_or_else(config::default_capture_timeout);
+
+    (
+        Duration::from_secs(seconds),
+        config::load_capture_settings(&directory),
+    )
+}
+
+/// Takes a photo into `output_path`, retrying once unless the camera is gone.
+/// Only returns `Ok` once the new photo is on disk and decodes.
+pub fn capture_still(
+    output_path: &str,
+    timeout: Duration,
+    settings: &CaptureSettings,
+) -> Result<(), CaptureError> {
+    let mut attempt = 1;
+
+    loop {
+        match capture_once(output_path, timeout, settings) {
+            Err(e) if attempt < CAPTURE_ATTEMPTS && e.is_retryable() => {
+                log::warn!("Capture attempt {} failed: {}", attempt, e);
+                thread::sleep(RETRY_DELAY);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn capture_once(
+    output_path: &str,
+    timeout: Duration,
+    settings: &CaptureSettings,
+) -> Result<(), CaptureError> {
+    preview::stop();
+    photos::forget(output_path);
+
+    // A leftover photo from the previous guest must never pass as this one
+    match fs::remove_file(output_path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(CaptureError::Failed(format!(
+                "Failed to clear {}: {}",
+                output_path, e
+            )));
+        }
+        _ => {}
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut cmd = Command::new("libcamera-still");
+        cmd.arg("-t")
+            .arg("3000")
+            .arg("--autofocus-mode")
+            .arg("continuous")
+            .arg("--autofocus-range")
+            .arg("normal")
+            .arg("--denoise")
+            .arg("cdn_off")
+            .arg("--shutter")
+            .arg(settings.shutter.to_string())
+            .arg("--gain")
+            .arg(settings.gain.to_string())
+            .arg("--ev")
+            .arg(settings.ev.to_string())
+            .arg("--roi")
+            .arg(CAPTURE_ROI)
+            .arg("--nopreview")
+            .arg("-o")
+            .arg(output_path);
+
+        // if color_mode != "B&W" {
+        //     cmd
+        //         .arg("--awbgains")
+        //         .arg("1.8,3.2");
+        // }
+
+        run(&mut cmd, timeout)?;
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (timeout, settings);
+        fs::copy("sample.jpg", output_path)
+            .map_err(|e| CaptureError::Failed(format!("Failed to copy sample image: {}", e)))?;
+        log::info!("Sample image copied to: {}", output_path);
+    }
+
+    photos::load(output_path, None).map_err(CaptureError::InvalidOutput)?;
+
+    Ok(())
+}
+
+/// Grabs `frames` frames `interval_ms` apart into `output_dir`, named
+/// `{prefix}-000.jpg` onwards, and returns their paths in order. When the
+/// burst fails, the frames it did write are deleted.
+pub fn capture_burst(
+    output_dir: &Path,
+    prefix: &str,
+    frames: u32,
+    interval_ms: u32,
+    timeout: Duration,
+    settings: &CaptureSettings,
+) -> Result<Vec<String>, CaptureError> {
+    preview::stop();
+
+    let captured = run_burst(output_dir, prefix, frames, interval_ms, timeout, settings);
+
+    // The timelapse can come up a frame short, so go by what was written
+    let paths: Vec<String> = (0..frames)
+        .map(|i| output_dir.join(format!("{}-{:03}.jpg", prefix, i)))
+        .filter(|path| path.exists())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    let result = captured.and_then(|()| {
+        if paths.len() < 2 {
+            return Err(CaptureError::InvalidOutput(format!(
+                "burst captured only {} frame(s)",
+                paths.len()
+            )));
+        }
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        for path in &paths {
+            if let Err(e) = fs::remove_file(path) {
+                log::warn!("Failed to delete burst frame {}: {}", path, e);
+            }
+        }
+        return Err(e);
+    }
+
+    Ok(paths)
+}
+
+fn run_burst(
+    output_dir: &Path,
+    prefix: &str,
+    frames: u32,
+    interval_ms: u32,
+    timeout: Duration,
+    settings: &CaptureSettings,
+) -> Result<(), CaptureError> {
+    #[cfg(target_os = "linux")]
+    {
+        let duration = frames * interval_ms;
+        let mut cmd = Command::new("libcamera-still");
+        cmd.arg("-t")
+            .arg(duration.to_string())
+            .arg("--timelapse")
+            .arg(interval_ms.to_string())
+            .arg("--framestart")
+            .arg("0")
+            .arg("--width")
+            .arg("1280")
+            .arg("--height")
+            .arg("720")
+            .arg("--ev")
+            .arg(settings.ev.to_string())
+            .arg("--roi")
+            .arg(CAPTURE_ROI)
+            .arg("--nopreview")
+            .arg("-o")
+            .arg(output_dir.join(format!("{}-%03d.jpg", prefix)));
+
+        run(&mut cmd, timeout + Duration::from_millis(duration as u64))?;
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (timeout, settings);
+        for i in 0..frames {
+            fs::copy(
+                "sample.jpg",
+                output_dir.join(format!("{}-{:03}.jpg", prefix, i)),
+            )
+            .map_err(|e| CaptureError::Failed(format!("Failed to copy sample image: {}", e)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a camera command to completion, killing it if it outlives `timeout`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn run(cmd: &mut Command, timeout: Duration) -> Result<(), CaptureError> {
+    let mut child = cmd
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| CaptureError::Failed(format!("Failed to execute camera command: {}", e)))?;
+
+    // Drained on the side so a chatty camera cannot fill the pipe and stall
+    let mut stderr = child.stderr.take();
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut output);
+        }
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(CaptureError::Timeout(timeout));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(CaptureError::Failed(e.to_string())),
+        }
+    };
+
+    let stderr = reader.join().unwrap_or_default();
+
+    if status.success() {
+        return Ok(());
+    }
+
+    log::debug!("libcamera stderr: {}", stderr);
+    Err(classify(&stderr, &status.to_string()))
+}
+
+/// Maps libcamera's error output onto the failures attendants can act on.
+fn classify(stderr: &str, status: &str) -> CaptureError {
+    let lower = stderr.to_lowercase();
+
+    if lower.contains("no cameras available") {
+        CaptureError::NotDetected
+    } else if lower.contains("device or resource busy")
+        || lower.contains("failed to acquire camera")
+    {
+        CaptureError::Busy
+    } else {
+        let reason = stderr
+            .lines()
+            .rev()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or(status);
+
+        CaptureError::Failed(reason.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `libcamera-still --list-cameras` on a Pi 5 with a Camera Module 3 and
+    /// an HQ camera.
+    const LISTING: &str = "Available cameras
+-----------------
+0 : imx708 [4608x2592 10-bit RGGB] (/base/axi/pcie@120000/rp1/i2c@88000/imx708@1a)
+    Modes: 'SRGGB10_CSI2P' : 1536x864 [120.13 fps - (768, 432)/3072x1728 crop]
+                             2304x1296 [56.03 fps - (0, 0)/4608x2592 crop]
+                             4608x2592 [14.35 fps - (0, 0)/4608x2592 crop]
+
+1 : imx477 [4056x3040 12-bit RGGB] (/base/axi/pcie@120000/rp1/i2c@80000/imx477@1a)
+    Modes: 'SRGGB10_CSI2P' : 1332x990 [120.05 fps - (696, 528)/2664x1980 crop]
+           'SRGGB12_CSI2P' : 2028x1080 [50.03 fps - (0, 440)/4056x2160 crop]
+                             2028x1520 [40.01 fps - (0, 0)/4056x3040 crop]
+                             4056x3040 [10.00 fps - (0, 0)/4056x3040 crop]
+";
+
+    #[test]
+    fn parses_the_camera_list() {
+        let cameras = parse_camera_list(LISTING);
+        assert_eq!(cameras.len(), 2);
+
+        let module = &cameras[0];
+        assert_eq!(module.index, 0);
+        assert_eq!(module.model, "imx708");
+        assert_eq!((module.width, module.height), (4608, 2592));
+        assert_eq!(module.format, "10-bit RGGB");
+        assert_eq!(
+            module.device,
+            "/base/axi/pcie@120000/rp1/i2c@88000/imx708@1a"
+        );
+        assert_eq!(module.modes.len(), 3);
+        assert_eq!((module.modes[0].width, module.modes[0].height), (1536, 864));
+        assert_eq!(module.modes[0].fps, 120.13);
+
+        let hq = &cameras[1];
+        assert_eq!(hq.index, 1);
+        assert_eq!(hq.model, "imx477");
+        assert_eq!(hq.format, "12-bit RGGB");
+        // Modes of both Bayer formats
+        assert_eq!(
+            hq.modes
+                .iter()
+                .map(|mode| (mode.width, mode.height))
+                .collect::<Vec<_>>(),
+            [(1332, 990), (2028, 1080), (2028, 1520), (4056, 3040)]
+        );
+        assert_eq!(hq.modes[3].fps, 10.0);
+    }
+
+    #[test]
+    fn parses_an_empty_camera_list() {
+        assert!(parse_camera_list("No cameras available!\n").is_empty());
+        assert!(parse_camera_list("").is_empty());
+    }
+
+    #[test]
+    fn classifies_libcamera_failures() {
+        let missing = "[0:00:01.084510436] [1873]  INFO Camera camera_manager.cpp:313 libcamera v0.3.2+99-1230f78d\nERROR: *** no cameras available ***\n";
+        assert!(matches!(
+            classify(missing, "exit status: 255"),
+            CaptureError::NotDetected
+        ));
+
+        let busy = "[0:02:13.301873215] [2210] ERROR V4L2 v4l2_videodevice.cpp:1906 /dev/video0[13:cap]: Failed to start streaming: Device or resource busy\nERROR: *** failed to start camera ***\n";
+        assert!(matches!(
+            classify(busy, "exit status: 255"),
+            CaptureError::Busy
+        ));
+
+        let acquire = "ERROR: *** failed to acquire camera /base/axi/pcie@120000/rp1/i2c@88000/imx708@1a ***\n";
+        assert!(matches!(
+            classify(acquire, "exit status: 255"),
+            CaptureError::Busy
+        ));
+    }
+
+    #[test]
+    fn reports_the_last_line_of_other_failures() {
+        let stderr = "[0:00:01.084510436] [1873]  INFO Camera camera_manager.cpp:313 libcamera v0.3.2\nERROR: *** failed to open /home/booth/Pictures/photo-1.jpg ***\n\n";
+
+        match classify(stderr, "exit status: 255") {
+            CaptureError::Failed(reason) => assert_eq!(
+                reason,
+                "ERROR: *** failed to open /home/booth/Pictures/photo-1.jpg ***"
+            ),
+            other => panic!("expected a failure, got {:?}", other),
+        }
+
+        match classify("", "signal: 9 (SIGKILL)") {
+            CaptureError::Failed(reason) => assert_eq!(reason, "signal: 9 (SIGKILL)"),
+            other => panic!("expected a failure, got {:?}", other),
+        }
+    }
+}
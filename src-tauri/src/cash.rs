@@ -0,0 +1,198 @@
+use std::{sync::Mutex, time::Instant};
+
+#[cfg(test)]
+use std::fs;
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{
+    auth,
+    config::{self, Attendant},
+    error::Error,
+    ledger::{self, PaymentMethod, Sale, SaleStatus},
+    logging,
+};
+
+const PINS_VERSION: u32 = 1;
+/// Attendant PIN hashes, kept out of config.json so they never reach the
+/// kiosk UI, the admin API or a diagnostics bundle.
+const PINS_FILE: &str = "attendants.json";
+
+static ATTEMPTS: Lazy<Mutex<auth::Attempts>> = Lazy::new(|| Mutex::new(auth::Attempts::default()));
+
+#[derive(Serialize, Deserialize, Clone)]
+struct AttendantPin {
+    id: String,
+    /// Argon2 PHC string of the attendant's PIN.
+    pin_hash: String,
+}
+
+/// Marks the session paid in cash once an attendant enters their PIN, for
+/// venues where UPI is not allowed or there is no connectivity. The amount
+/// is priced here from the plan for `copies`, plus the digital add-on when
+/// `digital` is set.
+#[tauri::command(async)]
+pub async fn record_cash_payment(
+    app: AppHandle,
+    copies: u8,
+    digital: bool,
+    pin: String,
+) -> Result<Sale, Error> {
+    let started = Instant::now();
+    let directory = ledger::document_dir(&app).map_err(Error::Storage)?;
+
+    let result =
+        tauri::async_runtime::spawn_blocking(move || approve(&directory, copies, digital, &pin))
+            .await
+            .map_err(|e| Error::Internal(format!("Cash approval failed: {}", e)))
+            .and_then(|approved| approved);
+    logging::command("record_cash_payment", started, &result);
+
+    result
+}
+
+fn approve(directory: &str, copies: u8, digital: bool, pin: &str) -> Result<Sale, Error> {
+    let config = config::load_config(directory)
+        .ok_or_else(|| Error::Invalid("The booth has not been configured".to_string()))?;
+    let amount = config::price(&config, copies, digital)?;
+    let attendants = config.attendants;
+    let pins = load_pins(directory);
+
+    let mut approver = None;
+    auth::guarded(&ATTEMPTS, "attendant", || {
+        for attendant in &attendants {
+            let Some(stored) = pins.iter().find(|stored| stored.id == attendant.id) else {
+                continue;
+            };
+
+            if auth::verify_hash(&stored.pin_hash, pin)? {
+                approver = Some(attendant.id.clone());
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    })?;
+    let attendant =
+        approver.ok_or_else(|| Error::Unauthorized("Incorrect attendant PIN".to_string()))?;
+
+    let mut sale = Sale::new(
+        format!("cash_{}", Utc::now().timestamp_millis()),
+        PaymentMethod::Cash,
+        amount,
+        SaleStatus::Paid,
+    );
+    sale.attendant = Some(attendant);
+
+    ledger::record(directory, sale.clone()).map_err(Error::Storage)?;
+    ledger::set_current(Some(sale.id.clone()));
+
+    log::info!(
+        "Cash sale {} of {} approved by {}",
+        sale.id,
+        amount,
+        sale.attendant.as_deref().unwrap_or_default()
+    );
+
+    Ok(sale)
+}
+
+/// Records that the attendant handed the cash for sale `id` back.
+pub fn refund(directory: &str, id: &str, reason: &str) -> Result<Sale, Error> {
+    let sale = ledger::find(directory, id)
+        .ok_or_else(|| Error::Invalid(format!("No sale {} in the ledger", id)))?;
+
+    match sale.status {
+        SaleStatus::Paid | SaleStatus::Fulfilled => {}
+        status => {
+            return Err(Error::Invalid(format!(
+                "Sale {} cannot be refunded while {:?}",
+                id, status
+            )))
+        }
+    }
+
+    ledger::update(directory, id, |sale| {
+        sale.status = SaleStatus::Refunded;
+        sale.note = Some(reason.to_string());
+    })
+    .map_err(Error::Storage)?
+    .ok_or_else(|| Error::Invalid(format!("No sale {} in the ledger", id)))
+}
+
+/// Hashes the PINs set on `attendants` into the PIN store and clears them.
+/// Attendants without a new PIN keep their old one, and the PINs of anyone
+/// no longer listed are dropped.
+pub fn store_pins(directory: &str, attendants: &mut [Attendant]) -> Result<(), Error> {
+    let stored = load_pins(directory);
+    let mut pins = Vec::new();
+
+    for attendant in attendants {
+        let pin = std::mem::take(&mut attendant.pin);
+
+        if !pin.is_empty() {
+            if !auth::is_valid_pin(&pin) {
+                return Err(Error::Invalid(format!(
+                    "The PIN for {} must be {} digits",
+                    attendant.id,
+                    auth::PIN_LENGTH
+                )));
+            }
+
+            pins.push(AttendantPin {
+                id: attendant.id.clone(),
+                pin_hash: auth::hash_pin(&pin)?,
+            });
+        } else if let Some(old) = stored.iter().find(|old| old.id == attendant.id) {
+            pins.push(old.clone());
+        }
+    }
+
+    config::save_versioned(directory, PINS_FILE, PINS_VERSION, &pins).map_err(Error::Storage)
+}
+
+fn load_pins(directory: &str) -> Vec<AttendantPin> {
+    config::load_versioned(directory, PINS_FILE, PINS_VERSION).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attendant(id: &str, pin: &str) -> Attendant {
+        Attendant {
+            id: id.to_string(),
+            pin: pin.to_string(),
+        }
+    }
+
+    fn booth(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("memorabooth-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn stores_only_numpad_pins() {
+        let directory = booth("cash-pins");
+
+        for pin in ["123", "12345", "12a4", "١٢٣٤"] {
+            let result = store_pins(&directory, &mut [attendant("asha", pin)]);
+            assert!(matches!(result, Err(Error::Invalid(_))), "{}", pin);
+        }
+        assert!(load_pins(&directory).is_empty());
+
+        store_pins(&directory, &mut [attendant("asha", "4821")]).unwrap();
+        let pins = load_pins(&directory);
+        assert_eq!(pins.len(), 1);
+        assert!(auth::verify_hash(&pins[0].pin_hash, "4821").unwrap());
+
+        // An empty PIN keeps the stored one
+        let mut attendants = [attendant("asha", "")];
+        store_pins(&directory, &mut attendants).unwrap();
+        assert_eq!(load_pins(&directory)[0].pin_hash, pins[0].pin_hash);
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs;
+
+use image::RgbaImage;
+use qcms::{DataType, Intent, Profile, Transform};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderingIntent {
+    #[default]
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl From<RenderingIntent> for Intent {
+    fn from(intent: RenderingIntent) -> Self {
+        match intent {
+            RenderingIntent::Perceptual => Intent::Perceptual,
+            RenderingIntent::RelativeColorimetric => Intent::RelativeColorimetric,
+            RenderingIntent::Saturation => Intent::Saturation,
+            RenderingIntent::AbsoluteColorimetric => Intent::AbsoluteColorimetric,
+        }
+    }
+}
+
+/// Converts `canvas` in place from sRGB into the RGB printer profile `icc`.
+pub fn to_printer_space(
+    canvas: &mut RgbaImage,
+    icc: &[u8],
+    intent: RenderingIntent,
+) -> Result<(), String> {
+    transform(icc, intent)?.apply(canvas.as_mut());
+
+    Ok(())
+}
+
+/// Checks that the ICC profile at `path` exists and can take sRGB prints, so
+/// a bad one is caught when it is set up rather than once a guest has paid.
+pub fn check_icc(path: &str, intent: RenderingIntent) -> Result<(), String> {
+    let icc = fs::read(path).map_err(|e| format!("Failed to read ICC profile {}: {}", path, e))?;
+
+    transform(&icc, intent)
+        .map(|_| ())
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+fn transform(icc: &[u8], intent: RenderingIntent) -> Result<Transform, String> {
+    let mut printer = Profile::new_from_slice(icc, false)
+        .ok_or_else(|| "Failed to parse printer ICC profile".to_string())?;
+    printer.precache_output_transform();
+
+    Transform::new(
+        &Profile::new_sRGB(),
+        &printer,
+        DataType::RGBA8,
+        intent.into(),
+    )
+    .ok_or_else(|| "Printer ICC profile cannot be used for RGB output".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// Matrix/TRC profile with Adobe RGB (1998) primaries and a 2.2 gamma.
+    const WIDE_RGB: &[u8] = include_bytes!("../testdata/wide-rgb.icc");
+
+    /// sRGB patches and where they land in `WIDE_RGB`, worked out from the
+    /// published primaries rather than by running the transform.
+    const PATCHES: [([u8; 3], [u8; 3]); 6] = [
+        ([255, 255, 255], [255, 255, 255]),
+        ([128, 128, 128], [127, 127, 127]),
+        ([0, 0, 0], [0, 0, 0]),
+        ([255, 0, 0], [219, 0, 0]),
+        ([0, 255, 0], [144, 255, 60]),
+        ([0, 0, 255], [0, 0, 250]),
+    ];
+    const TOLERANCE: u8 = 3;
+
+    #[test]
+    fn converts_srgb_patches_into_the_printer_profile() {
+        for intent in [
+            RenderingIntent::Perceptual,
+            RenderingIntent::RelativeColorimetric,
+        ] {
+            let mut canvas = RgbaImage::new(PATCHES.len() as u32, 1);
+            for (x, ([r, g, b], _)) in PATCHES.iter().enumerate() {
+                canvas.put_pixel(x as u32, 0, Rgba([*r, *g, *b, 255]));
+            }
+
+            to_printer_space(&mut canvas, WIDE_RGB, intent).unwrap();
+
+            for (x, (patch, expected)) in PATCHES.iter().enumerate() {
+                let Rgba([r, g, b, a]) = *canvas.get_pixel(x as u32, 0);
+
+                for (channel, want) in [r, g, b].iter().zip(expected) {
+                    assert!(
+                        channel.abs_diff(*want) <= TOLERANCE,
+                        "{:?} with {:?} became {:?}, expected {:?}",
+                        patch,
+                        intent,
+                        [r, g, b],
+                        expected
+                    );
+                }
+                assert_eq!(a, 255);
+            }
+        }
+    }
+
+    #[test]
+    fn checks_the_profile_file() {
+        let path = std::env::temp_dir().join("memorabooth-wide-rgb.icc");
+        fs::write(&path, WIDE_RGB).unwrap();
+        assert_eq!(
+            check_icc(&path.to_string_lossy(), RenderingIntent::Saturation),
+            Ok(())
+        );
+
+        let missing = check_icc("/nonexistent/printer.icc", RenderingIntent::Perceptual);
+        assert!(missing.unwrap_err().contains("Failed to read"));
+
+        let broken = std::env::temp_dir().join("memorabooth-broken.icc");
+        fs::write(&broken, &WIDE_RGB[..64]).unwrap();
+        assert!(check_icc(&broken.to_string_lossy(), RenderingIntent::Perceptual).is_err());
+    }
+
+    #[test]
+    fn rejects_a_broken_profile() {
+        let mut canvas = RgbaImage::new(1, 1);
+
+        assert!(
+            to_printer_space(&mut canvas, &WIDE_RGB[..64], RenderingIntent::Perceptual).is_err()
+        );
+    }
+}
@@ -1,12 +1,25 @@
-use std::{fs, path::PathBuf};
+use std::{collections::HashSet, fs, path::PathBuf};
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::imaging::Layout;
+use crate::{
+    animation::AnimationMode,
+    auth, cash,
+    color::{self, RenderingIntent},
+    error::Error,
+    imaging::{self, Layout},
+    logging::LogLevel,
+};
 
 const CONFIG_VERSSION: u32 = 1;
 const LAYOUTS_VERSION: u32 = 2;
 const PAGES_VERSION: u32 = 1;
+const PRINTERS_VERSION: u32 = 1;
+const CAPTURE_SETTINGS_VERSION: u32 = 1;
+
+/// Emitted when settings change behind the kiosk's back, e.g. from the fleet
+/// server or the admin API, so the frontend reloads them.
+pub const CONFIG_CHANGED: &str = "config-changed";
 
 #[derive(Serialize, Deserialize)]
 struct Versioned<T> {
@@ -31,8 +44,42 @@ pub struct Addon {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
-    plans: Vec<Plan>,
-    digital: Addon,
+    pub plans: Vec<Plan>,
+    pub digital: Addon,
+    #[serde(default)]
+    pub event_name: String,
+    #[serde(default)]
+    pub booth_id: String,
+    /// Text under the photos on every strip; the product name when unset.
+    #[serde(default)]
+    pub branding: Option<String>,
+    #[serde(default)]
+    pub smart_crop: bool,
+    /// Animation captured after the stills for the digital add-on, if any.
+    #[serde(default)]
+    pub animation: Option<AnimationMode>,
+    /// Seconds a single camera run may take before it is killed.
+    #[serde(default = "default_capture_timeout")]
+    pub capture_timeout: u64,
+    /// Staff who can approve cash payments with their PIN.
+    #[serde(default)]
+    pub attendants: Vec<Attendant>,
+    /// How much detail goes into the log files; applies from the next start.
+    #[serde(default)]
+    pub log_level: LogLevel,
+}
+
+pub fn default_capture_timeout() -> u64 {
+    15
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Attendant {
+    pub id: String,
+    /// A new PIN to set. It is hashed into the PIN store when the config is
+    /// saved and never written back out; leave it empty to keep the old one.
+    #[serde(default, skip_serializing)]
+    pub pin: String,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -43,13 +90,146 @@ pub struct LayoutData {
     pub disclaimer: String,
 }
 
-#[tauri::command]
-pub fn save_config(directory: String, config: Config) -> Result<(), String> {
+/// A printer and the paper loaded in it. Sizes are in inches.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrinterProfile {
+    pub name: String,
+    /// CUPS queue to submit to, or the system default when unset.
+    pub queue: Option<String>,
+    pub paper_width: f32,
+    pub paper_height: f32,
+    pub dpi: f32,
+    /// Unprintable edge the layout keeps clear of.
+    pub margin: f32,
+    /// Extra canvas rendered past each paper edge, for printers that crop it.
+    pub bleed: f32,
+    /// CUPS `media` option for full-page prints.
+    pub media: String,
+    /// Whether the printer cuts the page into 2-inch strips for Layout C.
+    pub cuts_strips: bool,
+    /// `media` option selecting the cut, or the queue's default when unset.
+    pub strip_media: Option<String>,
+    /// Path to the printer's ICC profile; prints are sent as sRGB when unset.
+    #[serde(default)]
+    pub icc_profile: Option<String>,
+    #[serde(default)]
+    pub rendering_intent: RenderingIntent,
+    /// Prints per paper pack; the page counter stops here until a refill.
+    #[serde(default = "default_paper_capacity")]
+    pub paper_capacity: u64,
+}
+
+pub fn default_paper_capacity() -> u64 {
+    700
+}
+
+impl Default for PrinterProfile {
+    fn default() -> Self {
+        Self {
+            name: "4x6".to_string(),
+            queue: None,
+            paper_width: 4.0,
+            paper_height: 6.0,
+            dpi: 300.0,
+            margin: 0.0,
+            bleed: 0.0,
+            media: "w288h432".to_string(),
+            cuts_strips: true,
+            strip_media: None,
+            icc_profile: None,
+            rendering_intent: RenderingIntent::default(),
+            paper_capacity: default_paper_capacity(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Printers {
+    pub active: String,
+    pub profiles: Vec<PrinterProfile>,
+}
+
+impl Printers {
+    /// The profile named by `active`, falling back to the first one.
+    pub fn active_profile(&self) -> Option<&PrinterProfile> {
+        self.profiles
+            .iter()
+            .find(|p| p.name == self.active)
+            .or_else(|| self.profiles.first())
+    }
+}
+
+/// Manual exposure for stills. Shutter is in microseconds.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CaptureSettings {
+    pub shutter: u32,
+    pub gain: f32,
+    /// Only applies while the camera meters itself, i.e. during bursts.
+    pub ev: f32,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        CaptureSettings {
+            shutter: 18000,
+            gain: 10.0,
+            ev: 0.0,
+        }
+    }
+}
+
+pub(crate) fn load_versioned<T: DeserializeOwned>(
+    directory: &str,
+    file: &str,
+    version: u32,
+) -> Option<T> {
+    let path = PathBuf::from(directory).join("Memorabooth").join(file);
+
+    let content = fs::read_to_string(path).ok()?;
+    let parsed = serde_json::from_str::<Versioned<T>>(&content).ok()?;
+
+    (parsed.version == version).then_some(parsed.data)
+}
+
+pub(crate) fn save_versioned<T: Serialize>(
+    directory: &str,
+    file: &str,
+    version: u32,
+    data: &T,
+) -> Result<(), String> {
     let mut path = PathBuf::from(directory);
 
     path.push("Memorabooth");
     fs::create_dir_all(&path).map_err(|e| e.to_string())?;
 
+    path.push(file);
+
+    let wrapped = Versioned { version, data };
+    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
+
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_config(directory: String, config: Config, token: String) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    write_config(directory, config)
+}
+
+/// Stores the config without an admin check, for the backend's own
+/// updates.
+pub fn write_config(directory: String, mut config: Config) -> Result<(), Error> {
+    if let Some(branding) = &config.branding {
+        imaging::check_branding(branding).map_err(Error::Invalid)?;
+    }
+    cash::store_pins(&directory, &mut config.attendants)?;
+
+    let mut path = PathBuf::from(directory);
+
+    path.push("Memorabooth");
+    fs::create_dir_all(&path).map_err(Error::storage)?;
+
     path.push("config.json");
 
     let wrapped = Versioned {
@@ -57,31 +237,103 @@ pub fn save_config(directory: String, config: Config) -> Result<(), String> {
         data: config,
     };
 
-    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
 
-    fs::write(path, json).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(Error::storage)?;
 
     Ok(())
 }
 
+/// Reads the stored config without creating it, for backend features that
+/// only need a few of its settings.
+pub fn load_config(directory: &str) -> Option<Config> {
+    load_versioned(directory, "config.json", CONFIG_VERSSION)
+}
+
+/// What a guest pays, in paise, for the plan printing `copies` strips and,
+/// if `digital`, the digital add-on. Prices are set in rupees.
+pub fn price(config: &Config, copies: u8, digital: bool) -> Result<u64, Error> {
+    let plan = config
+        .plans
+        .iter()
+        .find(|plan| plan.copies == copies)
+        .ok_or_else(|| Error::Invalid(format!("There is no plan for {} copies", copies)))?;
+    let addon = if !digital {
+        0
+    } else if config.digital.enabled {
+        config.digital.price
+    } else {
+        return Err(Error::Invalid(
+            "The digital add-on is not offered".to_string(),
+        ));
+    };
+
+    Ok((plan.price as u64 + addon as u64) * 100)
+}
+
+/// The stored printer profiles, or just the stock 4x6 profile when none have
+/// been set up.
+pub fn load_printers(directory: &str) -> Printers {
+    load_versioned::<Printers>(directory, "printers.json", PRINTERS_VERSION)
+        .filter(|printers| !printers.profiles.is_empty())
+        .unwrap_or_else(|| {
+            let profile = PrinterProfile::default();
+
+            Printers {
+                active: profile.name.clone(),
+                profiles: vec![profile],
+            }
+        })
+}
+
+/// The printer profile prints should be rendered for, with the stock 4x6
+/// profile when none have been set up.
+pub fn active_printer(directory: &str) -> PrinterProfile {
+    load_printers(directory)
+        .active_profile()
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// How many prints the stored page counter says have come off the current
+/// paper pack.
+pub fn load_pages(directory: &str) -> u64 {
+    load_versioned(directory, "pages.json", PAGES_VERSION).unwrap_or(0)
+}
+
+/// Prints left in the pack loaded in `profile`'s printer.
+pub fn paper_remaining(directory: &str, profile: &PrinterProfile) -> u64 {
+    profile.paper_capacity.saturating_sub(load_pages(directory))
+}
+
+/// The stored capture settings, or the stock exposure when none are saved.
+pub fn load_capture_settings(directory: &str) -> CaptureSettings {
+    load_versioned(directory, "capture.json", CAPTURE_SETTINGS_VERSION).unwrap_or_default()
+}
+
 #[tauri::command]
-pub fn get_or_init_config(directory: String, defaults: Config) -> Result<Config, String> {
-    let mut path = PathBuf::from(directory);
+pub fn get_or_init_config(directory: String, defaults: Config) -> Result<Config, Error> {
+    let mut path = PathBuf::from(&directory);
     path.push("Memorabooth");
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&path).map_err(Error::storage)?;
 
     path.push("config.json");
 
     if path.exists() {
-        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let content = fs::read_to_string(&path).map_err(Error::storage)?;
 
         if let Ok(parsed) = serde_json::from_str::<Versioned<Config>>(&content) {
             if parsed.version == CONFIG_VERSSION {
+                // Attendant PINs stored in the clear before they were hashed
+                if parsed.data.attendants.iter().any(|a| !a.pin.is_empty()) {
+                    write_config(directory, parsed.data.clone())?;
+                }
+
                 return Ok(parsed.data);
             }
         }
 
-        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        fs::remove_file(&path).map_err(Error::storage)?;
     }
 
     let wrapped = Versioned {
@@ -89,18 +341,30 @@ pub fn get_or_init_config(directory: String, defaults: Config) -> Result<Config,
         data: defaults.clone(),
     };
 
-    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
-    fs::write(&path, json).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+    fs::write(&path, json).map_err(Error::storage)?;
 
     Ok(defaults)
 }
 
 #[tauri::command]
-pub fn save_layouts(directory: String, layouts: Vec<LayoutData>) -> Result<(), String> {
+pub fn save_layouts(
+    directory: String,
+    layouts: Vec<LayoutData>,
+    token: String,
+) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    write_layouts(directory, layouts)
+}
+
+/// Stores the layouts without an admin check, for the backend's own
+/// updates.
+pub fn write_layouts(directory: String, layouts: Vec<LayoutData>) -> Result<(), Error> {
     let mut path = PathBuf::from(directory);
 
     path.push("Memorabooth");
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&path).map_err(Error::storage)?;
 
     path.push("layouts.json");
 
@@ -109,9 +373,9 @@ pub fn save_layouts(directory: String, layouts: Vec<LayoutData>) -> Result<(), S
         data: layouts,
     };
 
-    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
 
-    fs::write(path, json).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(Error::storage)?;
 
     Ok(())
 }
@@ -120,15 +384,15 @@ pub fn save_layouts(directory: String, layouts: Vec<LayoutData>) -> Result<(), S
 pub fn get_or_init_layouts(
     directory: String,
     defaults: Vec<LayoutData>,
-) -> Result<Vec<LayoutData>, String> {
+) -> Result<Vec<LayoutData>, Error> {
     let mut path = PathBuf::from(directory);
     path.push("Memorabooth");
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&path).map_err(Error::storage)?;
 
     path.push("layouts.json");
 
     if path.exists() {
-        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let content = fs::read_to_string(&path).map_err(Error::storage)?;
 
         if let Ok(parsed) = serde_json::from_str::<Versioned<Vec<LayoutData>>>(&content) {
             if parsed.version == LAYOUTS_VERSION {
@@ -136,7 +400,7 @@ pub fn get_or_init_layouts(
             }
         }
 
-        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        fs::remove_file(&path).map_err(Error::storage)?;
     }
 
     let wrapped = Versioned {
@@ -144,18 +408,43 @@ pub fn get_or_init_layouts(
         data: defaults.clone(),
     };
 
-    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
-    fs::write(&path, json).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+    fs::write(&path, json).map_err(Error::storage)?;
 
     Ok(defaults)
 }
 
 #[tauri::command]
-pub fn save_pages(directory: String, pages: u64) -> Result<(), String> {
+pub fn save_pages(directory: String, pages: u64, token: String) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    write_pages(directory, pages)
+}
+
+/// Adds `copies` prints to the page counter. It stops at the active
+/// printer's pack size, so an empty pack stays empty until it is refilled.
+pub fn count_pages(directory: &str, copies: u64) -> Result<(), Error> {
+    let capacity = active_printer(directory).paper_capacity;
+    let pages = (load_pages(directory) + copies).min(capacity);
+
+    write_pages(directory.to_string(), pages)
+}
+
+/// Stores the page counter without an admin check, for the backend's own
+/// updates. It can be at most the active printer's pack size.
+pub fn write_pages(directory: String, pages: u64) -> Result<(), Error> {
+    let capacity = active_printer(&directory).paper_capacity;
+    if pages > capacity {
+        return Err(Error::Invalid(format!(
+            "The page count can be at most the pack size of {}",
+            capacity
+        )));
+    }
+
     let mut path = PathBuf::from(directory);
 
     path.push("Memorabooth");
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&path).map_err(Error::storage)?;
 
     path.push("pages.json");
 
@@ -164,23 +453,23 @@ pub fn save_pages(directory: String, pages: u64) -> Result<(), String> {
         data: pages,
     };
 
-    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
 
-    fs::write(path, json).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(Error::storage)?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn get_or_init_pages(directory: String, default: u64) -> Result<u64, String> {
+pub fn get_or_init_pages(directory: String, default: u64) -> Result<u64, Error> {
     let mut path = PathBuf::from(directory);
     path.push("Memorabooth");
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&path).map_err(Error::storage)?;
 
     path.push("pages.json");
 
     if path.exists() {
-        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let content = fs::read_to_string(&path).map_err(Error::storage)?;
 
         if let Ok(parsed) = serde_json::from_str::<Versioned<u64>>(&content) {
             if parsed.version == PAGES_VERSION {
@@ -188,7 +477,7 @@ pub fn get_or_init_pages(directory: String, default: u64) -> Result<u64, String>
             }
         }
 
-        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        fs::remove_file(&path).map_err(Error::storage)?;
     }
 
     let wrapped = Versioned {
@@ -196,8 +485,237 @@ pub fn get_or_init_pages(directory: String, default: u64) -> Result<u64, String>
         data: default,
     };
 
-    let json = serde_json::to_string_pretty(&wrapped).map_err(|e| e.to_string())?;
-    fs::write(&path, json).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+    fs::write(&path, json).map_err(Error::storage)?;
 
     Ok(default)
 }
+
+/// Checks every profile can be printed with and that `active` names one.
+pub fn check_printers(printers: &Printers) -> Result<(), Error> {
+    let mut names = HashSet::new();
+
+    for profile in &printers.profiles {
+        if profile.name.trim().is_empty() {
+            return Err(Error::Invalid(
+                "Every printer profile needs a name".to_string(),
+            ));
+        }
+        if !names.insert(profile.name.as_str()) {
+            return Err(Error::Invalid(format!(
+                "There is more than one printer profile named {}",
+                profile.name
+            )));
+        }
+
+        imaging::check_profile(profile).map_err(Error::Invalid)?;
+
+        if let Some(icc_path) = &profile.icc_profile {
+            color::check_icc(icc_path, profile.rendering_intent)
+                .map_err(|e| Error::Invalid(format!("Printer profile {}: {}", profile.name, e)))?;
+        }
+    }
+
+    if !names.contains(printers.active.as_str()) {
+        return Err(Error::Invalid(format!(
+            "There is no printer profile named {} to print with",
+            printers.active
+        )));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn save_printers(directory: String, printers: Printers, token: String) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    write_printers(directory, printers)
+}
+
+/// Stores the printer profiles without an admin check, for the backend's own
+/// updates. They are checked the same way.
+pub fn write_printers(directory: String, printers: Printers) -> Result<(), Error> {
+    check_printers(&printers)?;
+
+    let mut path = PathBuf::from(directory);
+
+    path.push("Memorabooth");
+    fs::create_dir_all(&path).map_err(Error::storage)?;
+
+    path.push("printers.json");
+
+    let wrapped = Versioned {
+        version: PRINTERS_VERSION,
+        data: printers,
+    };
+
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+
+    fs::write(path, json).map_err(Error::storage)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_or_init_printers(directory: String, defaults: Printers) -> Result<Printers, Error> {
+    let mut path = PathBuf::from(directory);
+    path.push("Memorabooth");
+    fs::create_dir_all(&path).map_err(Error::storage)?;
+
+    path.push("printers.json");
+
+    if path.exists() {
+        let content = fs::read_to_string(&path).map_err(Error::storage)?;
+
+        if let Ok(parsed) = serde_json::from_str::<Versioned<Printers>>(&content) {
+            if parsed.version == PRINTERS_VERSION {
+                return Ok(parsed.data);
+            }
+        }
+
+        fs::remove_file(&path).map_err(Error::storage)?;
+    }
+
+    let wrapped = Versioned {
+        version: PRINTERS_VERSION,
+        data: defaults.clone(),
+    };
+
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+    fs::write(&path, json).map_err(Error::storage)?;
+
+    Ok(defaults)
+}
+
+#[tauri::command]
+pub fn save_capture_settings(
+    directory: String,
+    settings: CaptureSettings,
+    token: String,
+) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    write_capture_settings(directory, settings)
+}
+
+/// Stores the capture settings without an admin check, for the backend's own
+/// updates.
+pub fn write_capture_settings(directory: String, settings: CaptureSettings) -> Result<(), Error> {
+    let mut path = PathBuf::from(directory);
+
+    path.push("Memorabooth");
+    fs::create_dir_all(&path).map_err(Error::storage)?;
+
+    path.push("capture.json");
+
+    let wrapped = Versioned {
+        version: CAPTURE_SETTINGS_VERSION,
+        data: settings,
+    };
+
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+
+    fs::write(path, json).map_err(Error::storage)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_or_init_capture_settings(
+    directory: String,
+    defaults: CaptureSettings,
+) -> Result<CaptureSettings, Error> {
+    let mut path = PathBuf::from(directory);
+    path.push("Memorabooth");
+    fs::create_dir_all(&path).map_err(Error::storage)?;
+
+    path.push("capture.json");
+
+    if path.exists() {
+        let content = fs::read_to_string(&path).map_err(Error::storage)?;
+
+        if let Ok(parsed) = serde_json::from_str::<Versioned<CaptureSettings>>(&content) {
+            if parsed.version == CAPTURE_SETTINGS_VERSION {
+                return Ok(parsed.data);
+            }
+        }
+
+        fs::remove_file(&path).map_err(Error::storage)?;
+    }
+
+    let wrapped = Versioned {
+        version: CAPTURE_SETTINGS_VERSION,
+        data: defaults.clone(),
+    };
+
+    let json = serde_json::to_string_pretty(&wrapped).map_err(Error::storage)?;
+    fs::write(&path, json).map_err(Error::storage)?;
+
+    Ok(defaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printers(active: &str, names: &[&str]) -> Printers {
+        Printers {
+            active: active.to_string(),
+            profiles: names
+                .iter()
+                .map(|name| PrinterProfile {
+                    name: name.to_string(),
+                    ..PrinterProfile::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn checks_printer_names() {
+        assert!(check_printers(&printers("4x6", &["4x6", "Strips"])).is_ok());
+
+        for bad in [
+            printers("4x6", &["4x6", "4x6"]),
+            printers("4x6", &["4x6", " "]),
+            printers("Strips", &["4x6"]),
+            printers("4x6", &[]),
+        ] {
+            assert!(matches!(check_printers(&bad), Err(Error::Invalid(_))));
+        }
+    }
+
+    #[test]
+    fn checks_every_printer_profile() {
+        let mut bad = printers("4x6", &["4x6", "Strips"]);
+        bad.profiles[1].dpi = 0.0;
+
+        assert!(matches!(check_printers(&bad), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn prices_the_plan_and_add_on_in_paise() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "plans": [
+                { "title": "One", "price": 150, "copies": 1, "popular": false },
+                { "title": "Two", "price": 250, "copies": 2, "popular": true },
+            ],
+            "digital": { "title": "Digital", "price": 99, "enabled": true },
+        }))
+        .unwrap();
+
+        assert_eq!(price(&config, 1, false).unwrap(), 15_000);
+        assert_eq!(price(&config, 2, true).unwrap(), 34_900);
+        assert!(matches!(price(&config, 3, false), Err(Error::Invalid(_))));
+
+        let config = Config {
+            digital: Addon {
+                enabled: false,
+                ..config.digital.clone()
+            },
+            ..config
+        };
+        assert!(matches!(price(&config, 1, true), Err(Error::Invalid(_))));
+    }
+}
@@ -0,0 +1,243 @@
+use std::{fs, path::PathBuf};
+
+use chrono::{Local, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tauri::AppHandle;
+
+use crate::{
+    admin_api, auth, camera, config, error::Error, fleet, ledger, logging, mail, printer, readiness,
+};
+
+/// How many days of logs go into a bundle.
+const LOG_DAYS: u64 = 3;
+/// How many of the latest sales go into a bundle.
+const RECENT_SALES: usize = 50;
+/// Settings files copied into the bundle as they are stored. None of them
+/// hold a secret; the admin, attendant, admin API and fleet files do, so only
+/// summaries of those go in.
+const SETTINGS_FILES: [&str; 3] = ["layouts.json", "printers.json", "capture.json"];
+
+/// Packages what support needs to diagnose a booth into one zip in
+/// `destination`, e.g. a USB drive, and returns the archive's path.
+/// PIN hashes, tokens and guest email addresses are left out.
+#[tauri::command(async)]
+pub async fn export_diagnostics(
+    app: AppHandle,
+    destination: String,
+    token: String,
+) -> Result<String, Error> {
+    auth::require(&token)?;
+
+    let directory = ledger::document_dir(&app).map_err(Error::Storage)?;
+    let log_dir = logging::log_dir(&app).map_err(Error::Storage)?;
+    let archive = PathBuf::from(destination).join(format!(
+        "memorabooth-diagnostics-{}.zip",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    let path = archive.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut entries = collect(&directory)?;
+
+        for file in logging::recent_logs(&log_dir, LOG_DAYS).unwrap_or_default() {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            let data =
+                fs::read(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+
+            entries.push((format!("logs/{}", name), data));
+        }
+
+        logging::write_zip(&path, entries)
+    })
+    .await
+    .map_err(|e| Error::Storage(format!("Diagnostics export failed: {}", e)))?
+    .map_err(Error::Storage)?;
+
+    log::info!("Diagnostics exported to {}", archive.display());
+
+    Ok(archive.to_string_lossy().to_string())
+}
+
+fn collect(directory: &str) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let config = config::load_config(directory);
+    let sales = ledger::load(directory);
+    let printed = config::load_pages(directory);
+    let printer = config::active_printer(directory);
+
+    let mut entries = vec![
+        entry(
+            "manifest.json",
+            &json!({
+                "app_version": env!("CARGO_PKG_VERSION"),
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "booth_id": config.as_ref().map(|config| config.booth_id.clone()),
+                "event_name": config.as_ref().map(|config| config.event_name.clone()),
+                "created_at": Utc::now().timestamp(),
+            }),
+        )?,
+        entry("config.json", &config)?,
+        entry(
+            "paper.json",
+            &json!({
+                "printed": printed,
+                "printer": printer.name,
+                "capacity": printer.paper_capacity,
+                "remaining": printer.paper_capacity.saturating_sub(printed),
+            }),
+        )?,
+        entry("outbox.json", &mail::outbox(directory))?,
+        entry(
+            "sales.json",
+            &json!({
+                "report": ledger::report(&sales),
+                "recent": &sales[sales.len().saturating_sub(RECENT_SALES)..],
+            }),
+        )?,
+        entry("readiness.json", &readiness::check(directory, 1))?,
+        entry(
+            "printers.json",
+            &config::load_printers(directory)
+                .profiles
+                .iter()
+                .map(printer::status)
+                .collect::<Vec<_>>(),
+        )?,
+        entry(
+            "cameras.json",
+            &camera::cameras().map_err(|e| e.to_string()),
+        )?,
+        entry(
+            "fleet.json",
+            &json!({
+                "settings": fleet::load_settings(directory).map(|settings| json!({
+                    "enabled": settings.enabled,
+                    "endpoint": settings.endpoint,
+                    "interval_secs": settings.interval_secs,
+                })),
+                "state": fleet::load_state(directory),
+            }),
+        )?,
+        entry(
+            "admin-api.json",
+            &admin_api::load_settings(directory).map(|settings| {
+                json!({
+                    "enabled": settings.enabled,
+                    "address": settings.address,
+                    "port": settings.port,
+                })
+            }),
+        )?,
+    ];
+
+    for file in SETTINGS_FILES {
+        let path = PathBuf::from(directory).join("Memorabooth").join(file);
+
+        if let Ok(data) = fs::read(path) {
+            entries.push((format!("settings/{}", file), data));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn entry(name: &str, value: &impl Serialize) -> Result<(String, Vec<u8>), String> {
+    serde_json::to_vec_pretty(value)
+        .map(|data| (name.to_string(), data))
+        .map_err(|e| format!("Failed to serialise {}: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::Config;
+
+    const SECRET: &str = "s3cret-0123456789abcdef";
+
+    fn booth(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("memorabooth-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        let directory = dir.to_string_lossy().to_string();
+
+        let config: Config = serde_json::from_value(json!({
+            "plans": [],
+            "digital": { "title": "Digital", "price": 100, "enabled": false },
+            "event_name": "Wedding",
+            "booth_id": "booth-7",
+            "attendants": [{ "id": "asha", "pin": "4821" }],
+        }))
+        .unwrap();
+        config::write_config(directory.clone(), config).unwrap();
+
+        config::save_versioned(&directory, "admin.json", 1, &json!({ "pin_hash": SECRET }))
+            .unwrap();
+        config::save_versioned(
+            &directory,
+            "admin-api.json",
+            1,
+            &json!({ "enabled": true, "address": "192.168.4.1", "port": 8787, "token": SECRET }),
+        )
+        .unwrap();
+        config::save_versioned(
+            &directory,
+            "fleet.json",
+            1,
+            &json!({
+                "enabled": true,
+                "endpoint": "https://fleet.example.com",
+                "public_key": "",
+                "token": SECRET,
+                "interval_secs": 300,
+            }),
+        )
+        .unwrap();
+
+        directory
+    }
+
+    fn find(entries: &[(String, Vec<u8>)], name: &str) -> serde_json::Value {
+        let (_, data) = entries.iter().find(|(entry, _)| entry == name).unwrap();
+        serde_json::from_slice(data).unwrap()
+    }
+
+    #[test]
+    fn bundles_a_manifest_and_the_settings() {
+        let directory = booth("diagnostics-manifest");
+        let entries = collect(&directory).unwrap();
+
+        let manifest = find(&entries, "manifest.json");
+        assert_eq!(manifest["app_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest["booth_id"], "booth-7");
+        assert_eq!(manifest["event_name"], "Wedding");
+        assert!(manifest["created_at"].is_i64());
+
+        assert_eq!(find(&entries, "config.json")["attendants"][0]["id"], "asha");
+        assert_eq!(
+            find(&entries, "fleet.json")["settings"]["endpoint"],
+            "https://fleet.example.com"
+        );
+        assert_eq!(find(&entries, "admin-api.json")["port"], 8787);
+    }
+
+    #[test]
+    fn leaves_out_pins_and_tokens() {
+        let directory = booth("diagnostics-secrets");
+        let pin_hash = fs::read_to_string(
+            PathBuf::from(&directory)
+                .join("Memorabooth")
+                .join("attendants.json"),
+        )
+        .unwrap();
+        assert!(pin_hash.contains("argon2"));
+
+        for (name, data) in collect(&directory).unwrap() {
+            let text = String::from_utf8_lossy(&data);
+
+            assert!(!text.contains(SECRET), "{} holds a token", name);
+            assert!(!text.contains("argon2"), "{} holds a PIN hash", name);
+            assert!(!text.contains("4821"), "{} holds a PIN", name);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use crate::{camera::CaptureError, readiness::Problem};
+
+/// What went wrong in a command, sent to the frontend as
+/// `{ code, message, details }`: the message is safe to show guests, the
+/// details are for the attendant.
+#[derive(Debug)]
+pub enum Error {
+    /// A photo the command needs does not exist or is no longer available.
+    ImageMissing(String),
+    /// Rendering, encoding or saving an image failed.
+    Imaging(String),
+    PrinterOffline(String),
+    /// The printer is up but refused the job.
+    Print(String),
+    Mail(String),
+    Payment(String),
+    BoothNotReady(Vec<Problem>),
+    /// Another process still holds the camera.
+    CameraBusy(String),
+    CameraNotDetected(String),
+    /// The camera did not finish in time and was stopped.
+    CameraTimeout(String),
+    /// The camera ran but produced no usable photo.
+    Camera(String),
+    /// The request does not fit the booth's state, e.g. an unknown sale or a
+    /// photo slot the layout does not have.
+    Invalid(String),
+    /// Nothing exists at what was asked for, e.g. an unknown admin API route.
+    NotFound(String),
+    /// Reading or writing the files under `Memorabooth/` failed.
+    Storage(String),
+    /// The caller is not signed in as an admin.
+    Unauthorized(String),
+    Internal(String),
+    /// A print for the current sale failed, with the tries the guest has left
+    /// before the sale is given up on.
+    PrintFailed {
+        error: Box<Error>,
+        attempts_left: u32,
+    },
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    code: &'static str,
+    message: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Details<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts_left: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Details<'a> {
+    Text(&'a str),
+    Problems(&'a [Problem]),
+}
+
+impl Error {
+    pub fn storage(e: impl fmt::Display) -> Self {
+        Error::Storage(e.to_string())
+    }
+
+    /// Tells the guest how many more prints they may try, when a sale is
+    /// riding on this one.
+    pub fn with_attempts_left(self, attempts_left: Option<u32>) -> Self {
+        match attempts_left {
+            Some(attempts_left) => Error::PrintFailed {
+                error: Box::new(self),
+                attempts_left,
+            },
+            None => self,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ImageMissing(_) => "image_missing",
+            Error::Imaging(_) => "imaging",
+            Error::PrinterOffline(_) => "printer_offline",
+            Error::Print(_) => "print",
+            Error::Mail(_) => "mail",
+            Error::Payment(_) => "payment",
+            Error::BoothNotReady(_) => "booth_not_ready",
+            Error::CameraBusy(_) => "camera_busy",
+            Error::CameraNotDetected(_) => "camera_not_detected",
+            Error::CameraTimeout(_) => "camera_timeout",
+            Error::Camera(_) => "camera",
+            Error::Invalid(_) => "invalid",
+            Error::NotFound(_) => "not_found",
+            Error::Storage(_) => "storage",
+            Error::Unauthorized(_) => "unauthorized",
+            Error::Internal(_) => "internal",
+            Error::PrintFailed { error, .. } => error.code(),
+        }
+    }
+
+    /// A short explanation for guests.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Error::ImageMissing(_) => "Some of your photos could not be found",
+            Error::Imaging(_) => "We couldn't prepare your photos",
+            Error::PrinterOffline(_) => "The printer is not available right now",
+            Error::Print(_) => "Your photos could not be printed",
+            Error::Mail(_) => "We couldn't email your photos",
+            Error::Payment(_) => "We couldn't take your payment",
+            Error::BoothNotReady(_) => "The booth is not ready for a new session",
+            Error::CameraBusy(_) => "The camera is busy, please try again",
+            Error::CameraNotDetected(_) => "The camera is not connected",
+            Error::CameraTimeout(_) => "The camera took too long to respond",
+            Error::Camera(_) => "We couldn't take your photo",
+            Error::Invalid(_) => "That can't be done right now",
+            Error::NotFound(_) => "That could not be found",
+            Error::Storage(_) => "The booth could not save its settings",
+            Error::Unauthorized(_) => "Only an admin can do that",
+            Error::Internal(_) => "Something went wrong",
+            Error::PrintFailed { error, .. } => error.message(),
+        }
+    }
+
+    fn details(&self) -> Option<Details<'_>> {
+        match self {
+            Error::BoothNotReady(problems) => Some(Details::Problems(problems)),
+            Error::PrintFailed { error, .. } => error.details(),
+            Error::ImageMissing(detail)
+            | Error::Imaging(detail)
+            | Error::PrinterOffline(detail)
+            | Error::Print(detail)
+            | Error::Mail(detail)
+            | Error::Payment(detail)
+            | Error::CameraBusy(detail)
+            | Error::CameraNotDetected(detail)
+            | Error::CameraTimeout(detail)
+            | Error::Camera(detail)
+            | Error::Invalid(detail)
+            | Error::NotFound(detail)
+            | Error::Storage(detail)
+            | Error::Unauthorized(detail)
+            | Error::Internal(detail) => {
+                (!detail.is_empty()).then_some(Details::Text(detail.as_str()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(Details::Text(detail)) => f.write_str(detail),
+            Some(Details::Problems(problems)) => write!(
+                f,
+                "{}: {}",
+                self.message(),
+                serde_json::to_string(problems).unwrap_or_default()
+            ),
+            None => f.write_str(self.message()),
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Payload {
+            code: self.code(),
+            message: self.message(),
+            details: self.details(),
+            attempts_left: match self {
+                Error::PrintFailed { attempts_left, .. } => Some(*attempts_left),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<CaptureError> for Error {
+    fn from(error: CaptureError) -> Self {
+        let detail = error.to_string();
+
+        match error {
+            CaptureError::Busy => Error::CameraBusy(detail),
+            CaptureError::NotDetected => Error::CameraNotDetected(detail),
+            CaptureError::Timeout(_) => Error::CameraTimeout(detail),
+            CaptureError::Failed(_) | CaptureError::InvalidOutput(_) => Error::Camera(detail),
+        }
+    }
+}
+
+/// Lets helpers that report errors as text call the commands above.
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_failed_print_reports_the_attempts_left() {
+        let error = Error::Print("Paper jam".into());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "print",
+                "message": "Your photos could not be printed",
+                "details": "Paper jam"
+            })
+        );
+
+        let error = Error::Print("Paper jam".into()).with_attempts_left(Some(2));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "print",
+                "message": "Your photos could not be printed",
+                "details": "Paper jam",
+                "attempts_left": 2
+            })
+        );
+        assert_eq!(error.to_string(), "Paper jam");
+
+        // Without a sale there is nothing to count
+        let error = Error::Imaging(String::new()).with_attempts_left(None);
+        assert!(matches!(error, Error::Imaging(_)));
+    }
+}
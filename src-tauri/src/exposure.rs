@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use image::{imageops::FilterType::Triangle, DynamicImage};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    auth, camera,
+    config::{self, CaptureSettings},
+    error::Error,
+};
+
+const MEASURE_WIDTH: u32 = 640;
+// Luma at or past these counts as crushed shadows / blown highlights
+const SHADOW_CLIP: u8 = 5;
+const HIGHLIGHT_CLIP: u8 = 250;
+
+/// Mean luma that reads as a well exposed face under booth lighting.
+const TARGET_BRIGHTNESS: f32 = 118.0;
+const TOLERANCE: f32 = 10.0;
+/// More than this share of blown pixels counts as overexposed whatever the mean.
+const MAX_CLIPPED_HIGHLIGHTS: f32 = 2.0;
+const MAX_ITERATIONS: usize = 6;
+
+const MIN_SHUTTER: u32 = 100;
+// Slower than 1/30s blurs guests who are still settling into a pose
+const MAX_SHUTTER: u32 = 33000;
+const MIN_GAIN: f32 = 1.0;
+const MAX_GAIN: f32 = 16.0;
+// Caps each step so one odd frame cannot swing the exposure wildly
+const MAX_STEP: f32 = 8.0;
+// Luma is gamma encoded; this turns a brightness ratio into a light ratio
+const GAMMA: f32 = 2.2;
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct ExposureStats {
+    /// Mean luma, 0-255.
+    pub mean_brightness: f32,
+    /// Share of pixels, in percent, at or below the shadow clip point.
+    pub clipped_shadows: f32,
+    /// Share of pixels, in percent, at or above the highlight clip point.
+    pub clipped_highlights: f32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Calibration {
+    pub settings: CaptureSettings,
+    pub exposure: ExposureStats,
+    pub iterations: usize,
+    /// Whether the target was reached; when not, the closest settings the
+    /// shutter and gain limits allow are saved anyway.
+    pub converged: bool,
+}
+
+pub fn measure(frame: &DynamicImage) -> ExposureStats {
+    let luma = frame.resize(MEASURE_WIDTH, u32::MAX, Triangle).to_luma8();
+
+    let total = luma.len().max(1) as f32;
+    let (sum, shadows, highlights) = luma.iter().fold((0u64, 0u32, 0u32), |(sum, lo, hi), &v| {
+        (
+            sum + v as u64,
+            lo + (v <= SHADOW_CLIP) as u32,
+            hi + (v >= HIGHLIGHT_CLIP) as u32,
+        )
+    });
+
+    ExposureStats {
+        mean_brightness: sum as f32 / total,
+        clipped_shadows: shadows as f32 * 100.0 / total,
+        clipped_highlights: highlights as f32 * 100.0 / total,
+    }
+}
+
+/// Takes test frames, adjusting shutter and gain until the scene meets the
+/// target brightness, and stores the result as the capture settings.
+///
+/// EV is left as it is: with shutter and gain fixed the camera does not
+/// meter, so EV only biases the auto-exposed bursts.
+#[tauri::command(async)]
+pub async fn calibrate_exposure(app: AppHandle, token: String) -> Result<Calibration, Error> {
+    auth::require(&token)?;
+
+    let directory = app
+        .path()
+        .document_dir()
+        .map_err(|e| Error::Storage(format!("Failed to find documents directory: {}", e)))?
+        .to_string_lossy()
+        .to_string();
+    let (timeout, settings) = camera::capture_setup(&app);
+
+    let calibration = tauri::async_runtime::spawn_blocking(move || calibrate(timeout, settings))
+        .await
+        .map_err(|e| Error::Internal(format!("Exposure calibration failed: {}", e)))??;
+
+    config::write_capture_settings(directory, calibration.settings.clone())?;
+
+    Ok(calibration)
+}
+
+fn calibrate(timeout: Duration, mut settings: CaptureSettings) -> Result<Calibration, Error> {
+    let mut iterations = 0;
+
+    loop {
+        iterations += 1;
+
+        let exposure = camera::test_frame(timeout, &settings)?.exposure;
+        log::info!(
+            "Calibration {}: shutter {}us, gain {:.2} -> brightness {:.1}, highlights {:.1}%",
+            iterations,
+            settings.shutter,
+            settings.gain,
+            exposure.mean_brightness,
+            exposure.clipped_highlights
+        );
+
+        let converged = on_target(&exposure);
+        let next = adjust(&settings, &exposure);
+
+        if converged || iterations == MAX_ITERATIONS || at_limit(&settings, &next) {
+            return Ok(Calibration {
+                settings,
+                exposure,
+                iterations,
+                converged,
+            });
+        }
+
+        settings = next;
+    }
+}
+
+fn on_target(exposure: &ExposureStats) -> bool {
+    (exposure.mean_brightness - TARGET_BRIGHTNESS).abs() <= TOLERANCE
+        && exposure.clipped_highlights <= MAX_CLIPPED_HIGHLIGHTS
+}
+
+/// The settings that should bring `exposure` to the target, spending shutter
+/// time before gain since gain adds noise.
+fn adjust(settings: &CaptureSettings, exposure: &ExposureStats) -> CaptureSettings {
+    let mut ratio = (TARGET_BRIGHTNESS / exposure.mean_brightness.max(1.0)).powf(GAMMA);
+
+    // A window behind the guests blows out long before the mean looks bright
+    if exposure.clipped_highlights > MAX_CLIPPED_HIGHLIGHTS {
+        ratio = ratio.min(0.8);
+    }
+    let ratio = ratio.clamp(1.0 / MAX_STEP, MAX_STEP);
+
+    let light = settings.shutter as f32 * settings.gain * ratio;
+    let shutter = (light / MIN_GAIN).clamp(MIN_SHUTTER as f32, MAX_SHUTTER as f32);
+    let gain = (light / shutter).clamp(MIN_GAIN, MAX_GAIN);
+
+    CaptureSettings {
+        shutter: shutter.round() as u32,
+        gain: (gain * 100.0).round() / 100.0,
+        ev: settings.ev,
+    }
+}
+
+/// Whether the limits stop any further change.
+fn at_limit(current: &CaptureSettings, next: &CaptureSettings) -> bool {
+    current.shutter == next.shutter && (current.gain - next.gain).abs() < 0.01
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(shutter: u32, gain: f32) -> CaptureSettings {
+        CaptureSettings {
+            shutter,
+            gain,
+            ev: 0.5,
+        }
+    }
+
+    fn stats(mean_brightness: f32, clipped_highlights: f32) -> ExposureStats {
+        ExposureStats {
+            mean_brightness,
+            clipped_shadows: 0.0,
+            clipped_highlights,
+        }
+    }
+
+    #[test]
+    fn spends_shutter_before_gain() {
+        // Half the target brightness needs 2^2.2 times the light
+        let next = adjust(&settings(10000, 1.0), &stats(59.0, 0.0));
+
+        assert_eq!(next.shutter, MAX_SHUTTER);
+        assert_eq!(next.gain, 1.39);
+        assert_eq!(next.ev, 0.5);
+    }
+
+    #[test]
+    fn drops_gain_before_shutter() {
+        let next = adjust(&settings(20000, 2.0), &stats(236.0, 0.0));
+
+        assert_eq!(next.shutter, 8706);
+        assert_eq!(next.gain, MIN_GAIN);
+    }
+
+    #[test]
+    fn darkens_clipped_highlights_on_target() {
+        let next = adjust(&settings(10000, 1.0), &stats(TARGET_BRIGHTNESS, 5.0));
+
+        assert_eq!(next.shutter, 8000);
+        assert_eq!(next.gain, MIN_GAIN);
+    }
+
+    #[test]
+    fn limits_each_step() {
+        // A black frame would otherwise ask for unbounded light
+        let next = adjust(&settings(1000, 1.0), &stats(0.0, 0.0));
+
+        assert_eq!(next.shutter, 1000 * MAX_STEP as u32);
+        assert_eq!(next.gain, MIN_GAIN);
+    }
+
+    #[test]
+    fn stops_at_the_limits() {
+        let darkest = settings(MAX_SHUTTER, MAX_GAIN);
+        assert!(at_limit(&darkest, &adjust(&darkest, &stats(20.0, 0.0))));
+
+        let brightest = settings(MIN_SHUTTER, MIN_GAIN);
+        assert!(at_limit(
+            &brightest,
+            &adjust(&brightest, &stats(250.0, 40.0))
+        ));
+
+        let current = settings(10000, 1.0);
+        assert!(!at_limit(&current, &adjust(&current, &stats(59.0, 0.0))));
+        assert!(at_limit(&settings(10000, 2.0), &settings(10000, 2.005)));
+    }
+}
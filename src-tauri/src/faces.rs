@@ -0,0 +1,80 @@
+use image::DynamicImage;
+use once_cell::sync::Lazy;
+use rustface::{ImageData, Model};
+
+// Faces are searched for on a thumbnail, which keeps detection quick on the
+// Pi while group shots still have faces above the detector's 20px minimum.
+const DETECT_WIDTH: u32 = 640;
+
+// SeetaFace boxes hug the face, so pad them to take in hair and chin too.
+const HEAD_PADDING: f32 = 0.35;
+
+static FACE_MODEL: Lazy<Option<Model>> = Lazy::new(|| {
+    let model_src = include_bytes!("../models/seeta_fd_frontal_v1.0.bin");
+
+    match rustface::read_model(&model_src[..]) {
+        Ok(model) => Some(model),
+        Err(e) => {
+            log::error!("Failed to load face model: {}", e);
+            None
+        }
+    }
+});
+
+/// A head bounding box in source image pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct FaceBox {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// Finds the heads in `img`. Returns nothing if the model is unavailable.
+pub fn detect(img: &DynamicImage) -> Vec<FaceBox> {
+    let Some(model) = FACE_MODEL.as_ref() else {
+        return vec![];
+    };
+
+    let (orig_w, orig_h) = (img.width(), img.height());
+    let scale = (DETECT_WIDTH as f32 / orig_w as f32).min(1.0);
+
+    let small = img
+        .thumbnail(
+            (orig_w as f32 * scale).round() as u32,
+            (orig_h as f32 * scale).round() as u32,
+        )
+        .to_luma8();
+
+    let mut detector = rustface::create_detector_with_model(model.clone());
+    detector.set_min_face_size(20);
+    detector.set_score_thresh(2.0);
+    detector.set_pyramid_scale_factor(0.8);
+    detector.set_slide_window_step(4, 4);
+
+    let scale = small.width() as f32 / orig_w as f32;
+
+    detector
+        .detect(&ImageData::new(
+            small.as_raw(),
+            small.width(),
+            small.height(),
+        ))
+        .iter()
+        .map(|face| {
+            let bbox = face.bbox();
+
+            let pad_x = bbox.width() as f32 * HEAD_PADDING;
+            let pad_y = bbox.height() as f32 * HEAD_PADDING;
+
+            let to_source = |v: f32, max: u32| ((v / scale).max(0.0) as u32).min(max);
+
+            FaceBox {
+                left: to_source(bbox.x() as f32 - pad_x, orig_w),
+                top: to_source(bbox.y() as f32 - pad_y, orig_h),
+                right: to_source(bbox.x() as f32 + bbox.width() as f32 + pad_x, orig_w),
+                bottom: to_source(bbox.y() as f32 + bbox.height() as f32 + pad_y, orig_h),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,713 @@
+use std::{thread, time::Duration};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::Utc;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use once_cell::sync::Lazy;
+use reqwest::{Client, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter};
+
+use crate::{
+    auth,
+    config::{self, Addon, LayoutData, Plan},
+    error::Error,
+    ledger,
+    readiness::{self, Readiness},
+};
+
+const SETTINGS_VERSION: u32 = 1;
+const SETTINGS_FILE: &str = "fleet.json";
+const STATE_VERSION: u32 = 1;
+const STATE_FILE: &str = "fleet-state.json";
+/// How often the agent checks whether it has been switched on.
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+const MIN_INTERVAL_SECS: u64 = 30;
+/// How long one request to the fleet server may take, so a server that stops
+/// answering cannot stall the agent.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static FLEET_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .expect("Failed to build the fleet HTTP client")
+});
+
+/// How the booth reaches the fleet server.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FleetSettings {
+    pub enabled: bool,
+    /// Base URL of the fleet server. Must be HTTPS, except on localhost.
+    pub endpoint: String,
+    /// Base64 Ed25519 key the server signs config with.
+    pub public_key: String,
+    /// Bearer token identifying this booth to the server, if it needs one.
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+}
+
+fn default_interval() -> u64 {
+    300
+}
+
+/// What the agent remembers between syncs.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FleetState {
+    /// Version of the last fleet config applied.
+    pub config_version: u64,
+    /// Unix timestamps, in seconds.
+    pub last_pull: Option<i64>,
+    pub last_push: Option<i64>,
+    /// Ledger revision of the last sale change the server has; later ones
+    /// have not reached it yet. `None` until the first push.
+    #[serde(default)]
+    pub sales_synced_revision: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// A config document as served, with its detached signature.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Envelope {
+    /// Base64 JSON of a [`FleetConfig`].
+    pub payload: String,
+    /// Base64 Ed25519 signature over the decoded payload.
+    pub signature: String,
+}
+
+/// The settings the fleet manages. Anything left out stays as the booth has it.
+#[derive(Deserialize)]
+pub struct FleetConfig {
+    /// The booth the document was signed for, so one booth's config cannot
+    /// be replayed to another.
+    pub booth_id: String,
+    /// Increases with every change; a document older than the one applied is
+    /// rejected as a replay.
+    pub version: u64,
+    #[serde(default)]
+    pub plans: Option<Vec<Plan>>,
+    #[serde(default)]
+    pub digital: Option<Addon>,
+    #[serde(default)]
+    pub layouts: Option<Vec<LayoutData>>,
+    #[serde(default)]
+    pub event_name: Option<String>,
+    #[serde(default)]
+    pub branding: Option<String>,
+}
+
+#[tauri::command]
+pub fn save_fleet_settings(
+    directory: String,
+    settings: FleetSettings,
+    token: String,
+) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    check_endpoint(&settings.endpoint).map_err(Error::Invalid)?;
+
+    config::save_versioned(&directory, SETTINGS_FILE, SETTINGS_VERSION, &settings)
+        .map_err(Error::Storage)
+}
+
+#[tauri::command]
+pub fn get_or_init_fleet_settings(
+    directory: String,
+    defaults: FleetSettings,
+    token: String,
+) -> Result<FleetSettings, Error> {
+    auth::require(&token)?;
+
+    if let Some(settings) = load_settings(&directory) {
+        return Ok(settings);
+    }
+
+    config::save_versioned(&directory, SETTINGS_FILE, SETTINGS_VERSION, &defaults)
+        .map_err(Error::Storage)?;
+
+    Ok(defaults)
+}
+
+#[tauri::command]
+pub fn fleet_status(app: AppHandle) -> Result<FleetState, Error> {
+    Ok(load_state(
+        &ledger::document_dir(&app).map_err(Error::Storage)?,
+    ))
+}
+
+/// Syncs with the fleet server straight away instead of waiting for the
+/// next round.
+#[tauri::command(async)]
+pub async fn sync_fleet(app: AppHandle, token: String) -> Result<FleetState, Error> {
+    auth::require(&token)?;
+
+    let directory = ledger::document_dir(&app).map_err(Error::Storage)?;
+    let settings = load_settings(&directory)
+        .filter(|settings| settings.enabled)
+        .ok_or_else(|| Error::Invalid("Fleet sync is not enabled".to_string()))?;
+
+    sync(&app, &directory, &settings).await;
+
+    Ok(load_state(&directory))
+}
+
+/// Runs the sync agent in the background. It does nothing until fleet sync
+/// is enabled, and picks up settings changes on its next round.
+pub fn spawn_agent(app: AppHandle) {
+    thread::spawn(move || loop {
+        let settings = ledger::document_dir(&app)
+            .ok()
+            .and_then(|directory| load_settings(&directory).map(|s| (directory, s)))
+            .filter(|(_, settings)| settings.enabled);
+
+        let Some((directory, settings)) = settings else {
+            thread::sleep(IDLE_INTERVAL);
+            continue;
+        };
+
+        tauri::async_runtime::block_on(sync(&app, &directory, &settings));
+
+        thread::sleep(Duration::from_secs(
+            settings.interval_secs.max(MIN_INTERVAL_SECS),
+        ));
+    });
+}
+
+pub fn load_settings(directory: &str) -> Option<FleetSettings> {
+    config::load_versioned(directory, SETTINGS_FILE, SETTINGS_VERSION)
+}
+
+pub fn load_state(directory: &str) -> FleetState {
+    config::load_versioned(directory, STATE_FILE, STATE_VERSION).unwrap_or_default()
+}
+
+/// Runs one sync round and tells the kiosk to reload when it applied new
+/// config.
+async fn sync(app: &AppHandle, directory: &str, settings: &FleetSettings) {
+    let applied = round(directory, settings, readiness::check).await;
+
+    if let Some(version) = applied {
+        if let Err(e) = app.emit(config::CONFIG_CHANGED, version) {
+            log::warn!("Failed to announce fleet config: {}", e);
+        }
+    }
+}
+
+/// Pulls config, pushes a heartbeat and pushes the sales the server has not
+/// seen, recording the outcome in the fleet state. Each step runs even when
+/// an earlier one failed, so a booth the server has no config for still
+/// reports in. A failed sales push leaves the watermark where it was, so
+/// nothing is lost while offline. `check` assesses the booth for the
+/// heartbeat. Returns the config version applied, if any.
+async fn round(
+    directory: &str,
+    settings: &FleetSettings,
+    check: fn(&str, u64) -> Readiness,
+) -> Option<u64> {
+    let mut state = load_state(directory);
+    let booth_id = config::load_config(directory)
+        .map(|config| config.booth_id)
+        .unwrap_or_default();
+
+    let mut errors = Vec::new();
+    let mut applied = None;
+
+    if let Err(e) = check_endpoint(&settings.endpoint) {
+        errors.push(e);
+    } else if booth_id.is_empty() {
+        errors.push("The booth has no booth id".to_string());
+    } else {
+        match pull(directory, settings, &booth_id, &mut state).await {
+            Ok(changed) => {
+                state.last_pull = Some(Utc::now().timestamp());
+                applied = changed.then_some(state.config_version);
+            }
+            Err(e) => errors.push(e),
+        }
+
+        let readiness = check(directory, 1);
+        match heartbeat(directory, settings, &booth_id, &state, &readiness).await {
+            Ok(()) => state.last_push = Some(Utc::now().timestamp()),
+            Err(e) => errors.push(e),
+        }
+
+        if let Err(e) = push_sales(directory, settings, &booth_id, &mut state).await {
+            errors.push(e);
+        }
+    }
+
+    for e in &errors {
+        log::warn!("Fleet sync failed: {}", e);
+    }
+    state.last_error = (!errors.is_empty()).then(|| errors.join("; "));
+
+    if let Err(e) = config::save_versioned(directory, STATE_FILE, STATE_VERSION, &state) {
+        log::error!("Failed to save fleet state: {}", e);
+    }
+
+    applied
+}
+
+/// Fetches the booth's config and applies it when it is signed by the fleet
+/// key for this booth and newer than what the booth runs. Returns whether it
+/// was applied.
+async fn pull(
+    directory: &str,
+    settings: &FleetSettings,
+    booth_id: &str,
+    state: &mut FleetState,
+) -> Result<bool, String> {
+    let res = authorise(
+        FLEET_CLIENT.get(url(settings, booth_id, "config")),
+        settings,
+    )
+    .send()
+    .await
+    .map_err(|e| format!("Failed to fetch fleet config: {}", e))?;
+
+    // The server has no config for this booth yet
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
+    if !res.status().is_success() {
+        return Err(format!("Failed to fetch fleet config: {}", res.status()));
+    }
+
+    let envelope: Envelope = res
+        .json()
+        .await
+        .map_err(|e| format!("Parse error: {}", e))?;
+    let fleet_config = verify(&envelope, &settings.public_key)?;
+
+    if fleet_config.booth_id != booth_id {
+        return Err(format!(
+            "Fleet config is for booth {}, not {}",
+            fleet_config.booth_id, booth_id
+        ));
+    }
+    if fleet_config.version < state.config_version {
+        return Err(format!(
+            "Fleet config version {} is older than the applied version {}",
+            fleet_config.version, state.config_version
+        ));
+    }
+    if fleet_config.version == state.config_version {
+        return Ok(false);
+    }
+
+    apply(directory, &fleet_config)?;
+    log::info!("Applied fleet config version {}", fleet_config.version);
+
+    state.config_version = fleet_config.version;
+
+    Ok(true)
+}
+
+async fn heartbeat(
+    directory: &str,
+    settings: &FleetSettings,
+    booth_id: &str,
+    state: &FleetState,
+    readiness: &Readiness,
+) -> Result<(), String> {
+    let heartbeat = json!({
+        "booth_id": booth_id,
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "config_version": state.config_version,
+        "paper_remaining": config::paper_remaining(directory, &config::active_printer(directory)),
+        "readiness": readiness,
+        "sent_at": Utc::now().timestamp(),
+    });
+
+    post(settings, booth_id, "heartbeat", &heartbeat).await
+}
+
+async fn push_sales(
+    directory: &str,
+    settings: &FleetSettings,
+    booth_id: &str,
+    state: &mut FleetState,
+) -> Result<(), String> {
+    let sales = ledger::load(directory);
+
+    let changed: Vec<_> = sales
+        .iter()
+        .filter(|sale| {
+            state
+                .sales_synced_revision
+                .map_or(true, |synced| sale.revision > synced)
+        })
+        .collect();
+    let Some(synced) = changed.iter().map(|sale| sale.revision).max() else {
+        return Ok(());
+    };
+
+    let summary = json!({
+        "booth_id": booth_id,
+        "report": ledger::report(&sales),
+        "sales": changed,
+    });
+    post(settings, booth_id, "sales", &summary).await?;
+    state.sales_synced_revision = Some(synced);
+
+    Ok(())
+}
+
+async fn post(
+    settings: &FleetSettings,
+    booth_id: &str,
+    resource: &str,
+    body: &serde_json::Value,
+) -> Result<(), String> {
+    let res = authorise(
+        FLEET_CLIENT.post(url(settings, booth_id, resource)),
+        settings,
+    )
+    .json(body)
+    .send()
+    .await
+    .map_err(|e| format!("Failed to send {}: {}", resource, e))?;
+
+    if !res.status().is_success() {
+        return Err(format!("Failed to send {}: {}", resource, res.status()));
+    }
+
+    Ok(())
+}
+
+/// Checks `envelope` against the fleet key and decodes its config.
+pub fn verify(envelope: &Envelope, public_key: &str) -> Result<FleetConfig, String> {
+    let key: [u8; 32] = BASE64_STANDARD
+        .decode(public_key.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| "The fleet public key is not a base64 Ed25519 key".to_string())?;
+    let key =
+        VerifyingKey::from_bytes(&key).map_err(|e| format!("Invalid fleet public key: {}", e))?;
+
+    let payload = BASE64_STANDARD
+        .decode(&envelope.payload)
+        .map_err(|e| format!("Invalid fleet config payload: {}", e))?;
+    let signature = BASE64_STANDARD
+        .decode(&envelope.signature)
+        .ok()
+        .and_then(|signature| Signature::from_slice(&signature).ok())
+        .ok_or_else(|| "Invalid fleet config signature".to_string())?;
+
+    key.verify(&payload, &signature)
+        .map_err(|_| "Fleet config signature does not match".to_string())?;
+
+    serde_json::from_slice(&payload).map_err(|e| format!("Invalid fleet config: {}", e))
+}
+
+fn apply(directory: &str, fleet_config: &FleetConfig) -> Result<(), String> {
+    if let Some(layouts) = &fleet_config.layouts {
+        config::write_layouts(directory.to_string(), layouts.clone())?;
+    }
+
+    let managed = fleet_config.plans.is_some()
+        || fleet_config.digital.is_some()
+        || fleet_config.event_name.is_some()
+        || fleet_config.branding.is_some();
+    if !managed {
+        return Ok(());
+    }
+
+    let mut config = config::load_config(directory)
+        .ok_or_else(|| "The booth has to be set up once before fleet config applies".to_string())?;
+
+    if let Some(plans) = &fleet_config.plans {
+        config.plans = plans.clone();
+    }
+    if let Some(digital) = &fleet_config.digital {
+        config.digital = digital.clone();
+    }
+    if let Some(event_name) = &fleet_config.event_name {
+        config.event_name = event_name.clone();
+    }
+    if let Some(branding) = &fleet_config.branding {
+        config.branding = Some(branding.clone());
+    }
+
+    config::write_config(directory.to_string(), config)?;
+
+    Ok(())
+}
+
+/// Fleet traffic carries sales and config, so it only goes over HTTPS; plain
+/// HTTP is allowed to localhost for testing against a local server.
+fn check_endpoint(endpoint: &str) -> Result<(), String> {
+    let url =
+        Url::parse(endpoint).map_err(|e| format!("Invalid fleet endpoint {}: {}", endpoint, e))?;
+    let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if local => Ok(()),
+        _ => Err(format!("Fleet endpoint {} must use HTTPS", endpoint)),
+    }
+}
+
+fn url(settings: &FleetSettings, booth_id: &str, resource: &str) -> String {
+    format!(
+        "{}/booths/{}/{}",
+        settings.endpoint.trim_end_matches('/'),
+        booth_id,
+        resource
+    )
+}
+
+fn authorise(request: RequestBuilder, settings: &FleetSettings) -> RequestBuilder {
+    match &settings.token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use ed25519_dalek::{Signer, SigningKey};
+    use serde_json::Value;
+    use tiny_http::{Method, Response, Server};
+
+    use super::*;
+    use crate::ledger::{PaymentMethod, Sale, SaleStatus};
+
+    const FLEET_KEY: [u8; 32] = [7; 32];
+
+    /// A booth set up with a config and one sale, in a fresh directory.
+    fn booth(name: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("memorabooth-fleet-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        let directory = directory.to_string_lossy().to_string();
+
+        let config = serde_json::from_value(json!({
+            "plans": [],
+            "digital": { "title": "Digital", "price": 100, "enabled": false },
+            "event_name": "Before",
+            "booth_id": "booth-1",
+        }))
+        .unwrap();
+        config::write_config(directory.clone(), config).unwrap();
+        ledger::record(
+            &directory,
+            Sale::new("qr_1".into(), PaymentMethod::Upi, 100, SaleStatus::Paid),
+        )
+        .unwrap();
+
+        directory
+    }
+
+    fn envelope(key: &SigningKey, config: Value) -> Envelope {
+        let payload = config.to_string();
+
+        Envelope {
+            payload: BASE64_STANDARD.encode(&payload),
+            signature: BASE64_STANDARD.encode(key.sign(payload.as_bytes()).to_bytes()),
+        }
+    }
+
+    /// Serves `envelope` as the booth's config on a local fleet server,
+    /// returning its settings and the paths posted to it.
+    fn serve(envelope: Envelope) -> (FleetSettings, Arc<Mutex<Vec<String>>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let posted = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = posted.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let body = if *request.method() == Method::Get {
+                    serde_json::to_string(&envelope).unwrap()
+                } else {
+                    seen.lock().unwrap().push(request.url().to_string());
+                    "{}".to_string()
+                };
+                let _ = request.respond(Response::from_string(body));
+            }
+        });
+
+        let settings = FleetSettings {
+            enabled: true,
+            endpoint: format!("http://127.0.0.1:{}", port),
+            public_key: BASE64_STANDARD.encode(
+                SigningKey::from_bytes(&FLEET_KEY)
+                    .verifying_key()
+                    .to_bytes(),
+            ),
+            token: None,
+            interval_secs: 300,
+        };
+
+        (settings, posted)
+    }
+
+    fn event_name(directory: &str) -> String {
+        config::load_config(directory).unwrap().event_name
+    }
+
+    /// Stands in for [`readiness::check`], which probes the real camera and
+    /// printer.
+    fn ready(_: &str, _: u64) -> Readiness {
+        Readiness {
+            ready: true,
+            problems: Vec::new(),
+        }
+    }
+
+    fn sync_round(directory: &str, settings: &FleetSettings) -> Option<u64> {
+        tauri::async_runtime::block_on(round(directory, settings, ready))
+    }
+
+    #[test]
+    fn applies_signed_config() {
+        let directory = booth("applies");
+        let key = SigningKey::from_bytes(&FLEET_KEY);
+        let (settings, posted) = serve(envelope(
+            &key,
+            json!({
+                "booth_id": "booth-1",
+                "version": 2,
+                "event_name": "Wedding",
+                "branding": "Asha & Ravi",
+            }),
+        ));
+
+        let applied = sync_round(&directory, &settings);
+
+        assert_eq!(applied, Some(2));
+        assert_eq!(event_name(&directory), "Wedding");
+        assert_eq!(
+            config::load_config(&directory).unwrap().branding.as_deref(),
+            Some("Asha & Ravi")
+        );
+
+        let state = load_state(&directory);
+        assert_eq!(state.config_version, 2);
+        assert_eq!(state.last_error, None);
+        assert_eq!(
+            *posted.lock().unwrap(),
+            ["/booths/booth-1/heartbeat", "/booths/booth-1/sales"]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let directory = booth("bad-signature");
+        let forger = SigningKey::from_bytes(&[9; 32]);
+        let (settings, posted) = serve(envelope(
+            &forger,
+            json!({ "booth_id": "booth-1", "version": 2, "event_name": "Forged" }),
+        ));
+
+        let applied = sync_round(&directory, &settings);
+
+        assert_eq!(applied, None);
+        assert_eq!(event_name(&directory), "Before");
+
+        let state = load_state(&directory);
+        assert_eq!(state.config_version, 0);
+        assert!(state
+            .last_error
+            .is_some_and(|e| e.contains("signature does not match")));
+        // The heartbeat and sales still go out
+        assert_eq!(posted.lock().unwrap().len(), 2);
+    }
+
+    /// A booth whose fleet state says `version` is applied.
+    fn applied(name: &str, version: u64) -> String {
+        let directory = booth(name);
+        let state = FleetState {
+            config_version: version,
+            ..Default::default()
+        };
+        config::save_versioned(&directory, STATE_FILE, STATE_VERSION, &state).unwrap();
+
+        directory
+    }
+
+    #[test]
+    fn ignores_the_applied_version() {
+        let directory = applied("same", 5);
+        let key = SigningKey::from_bytes(&FLEET_KEY);
+        let (settings, _) = serve(envelope(
+            &key,
+            json!({ "booth_id": "booth-1", "version": 5, "event_name": "Again" }),
+        ));
+
+        assert_eq!(sync_round(&directory, &settings), None);
+        assert_eq!(event_name(&directory), "Before");
+        assert_eq!(load_state(&directory).last_error, None);
+    }
+
+    #[test]
+    fn rejects_older_version() {
+        let directory = applied("older", 5);
+        let key = SigningKey::from_bytes(&FLEET_KEY);
+        let (settings, _) = serve(envelope(
+            &key,
+            json!({ "booth_id": "booth-1", "version": 3, "event_name": "Old" }),
+        ));
+
+        assert_eq!(sync_round(&directory, &settings), None);
+        assert_eq!(event_name(&directory), "Before");
+
+        let state = load_state(&directory);
+        assert_eq!(state.config_version, 5);
+        assert!(state
+            .last_error
+            .is_some_and(|e| e.contains("older than the applied version 5")));
+    }
+
+    #[test]
+    fn rejects_config_for_another_booth() {
+        let directory = booth("other-booth");
+        let key = SigningKey::from_bytes(&FLEET_KEY);
+        let (settings, _) = serve(envelope(
+            &key,
+            json!({ "booth_id": "booth-2", "version": 2, "event_name": "Elsewhere" }),
+        ));
+
+        assert_eq!(sync_round(&directory, &settings), None);
+        assert_eq!(event_name(&directory), "Before");
+
+        let state = load_state(&directory);
+        assert_eq!(state.config_version, 0);
+        assert!(state
+            .last_error
+            .is_some_and(|e| e.contains("for booth booth-2, not booth-1")));
+    }
+
+    #[test]
+    fn rejects_unsigned_booth_id() {
+        let directory = booth("unsigned-booth");
+        let key = SigningKey::from_bytes(&FLEET_KEY);
+        let (settings, _) = serve(envelope(&key, json!({ "version": 2, "event_name": "Any" })));
+
+        assert_eq!(sync_round(&directory, &settings), None);
+        assert_eq!(event_name(&directory), "Before");
+        assert!(load_state(&directory)
+            .last_error
+            .is_some_and(|e| e.contains("booth_id")));
+    }
+
+    #[test]
+    fn endpoint_must_be_https_or_localhost() {
+        assert!(check_endpoint("https://fleet.example.com").is_ok());
+        assert!(check_endpoint("http://localhost:8080").is_ok());
+        assert!(check_endpoint("http://127.0.0.1/").is_ok());
+        assert!(check_endpoint("http://example.com").is_err());
+        assert!(check_endpoint("http://localhost.evil.com").is_err());
+        assert!(check_endpoint("http://127.0.0.1.evil.com").is_err());
+    }
+}
@@ -1,15 +1,59 @@
-use std::{path::PathBuf, process::Command};
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
 
 use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
-use image::{GenericImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::Utc;
+use image::{
+    codecs::jpeg::JpegEncoder, DynamicImage, GenericImage, GenericImageView, ImageBuffer, Rgba,
+    RgbaImage,
+};
 use imageproc::drawing::draw_text_mut;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
 
-const DPI: f32 = 300.0f32;
-const WIDTH: u32 = (4f32 * DPI).round() as u32;
-const HEIGHT: u32 = (6f32 * DPI).round() as u32;
+use crate::{
+    auth, color,
+    config::{self, PrinterProfile},
+    error::Error,
+    faces, ledger, logging, photos, printer, session,
+};
+
 const BORDER: f32 = 0.15f32;
+/// Largest DPI and paper side, in inches, a printer profile may have; well
+/// past any photo printer.
+const MAX_DPI: f32 = 2400.0;
+const MAX_PAPER: f32 = 24.0;
+/// Longest canvas side in pixels, bleed included. 12000px square is about
+/// 550 MB of RGBA, and 4x6 at 2400 DPI still fits.
+const MAX_CANVAS_SIDE: f32 = 12000.0;
+/// Largest share of the shorter paper side the margin and the bleed may
+/// each take.
+const MAX_EDGE_SHARE: f32 = 0.25;
+/// Text printed under the photos unless the caller or the config brands the
+/// strip.
+const DEFAULT_BRANDING: &str = "memora.";
+/// Longest branding accepted; longer text would shrink to be unreadable.
+const MAX_BRANDING: usize = 24;
+/// Font the branding is set in.
+const LABEL_FONT: &[u8] = include_bytes!("../fonts/Burgundia.otf");
+const PREVIEW_HEIGHT: u32 = 900;
+const PREVIEW_CAPACITY: usize = 4;
+
+static PREVIEWS: Lazy<Mutex<VecDeque<(String, RenderedPrint)>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+static PREVIEW_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum Layout {
@@ -18,402 +62,730 @@ pub enum Layout {
     C,
 }
 
-#[tauri::command(async)]
-pub async fn capture(output_path: &str) -> Result<String, String> {
-    #[cfg(target_os = "linux")]
-    {
-        let mut cmd_base = Command::new("libcamera-still");
-        let cmd = cmd_base
-            .arg("-t")
-            .arg("3000")
-            .arg("--autofocus-mode")
-            .arg("continuous")
-            .arg("--autofocus-range")
-            .arg("normal")
-            .arg("--denoise")
-            .arg("cdn_off")
-            .arg("--shutter")
-            .arg("18000")
-            .arg("--gain")
-            .arg("10")
-            .arg("--ev")
-            .arg("0")
-            .arg("--roi")
-            .arg("0.075,0.15,0.79,0.85")
-            .arg("-p")
-            .arg("-10,-10,1920,1080")
-            .arg("-o")
-            .arg(output_path);
-
-        // if color_mode != "B&W" {
-        //     cmd
-        //         .arg("--awbgains")
-        //         .arg("1.8,3.2");
-        // }
-
-        let result = cmd.output();
-
-        return match result {
-            Ok(output) => {
-                let stdout_str = String::from_utf8_lossy(&output.stdout);
-                let stderr_str = String::from_utf8_lossy(&output.stderr);
+impl Layout {
+    /// How many photos the layout is made of.
+    pub fn slots(&self) -> usize {
+        match self {
+            Layout::A => 2,
+            Layout::B | Layout::C => 4,
+        }
+    }
+}
 
-                if !output.status.success() {
-                    println!("stderr: {}", stderr_str);
-                }
+#[derive(Serialize)]
+pub struct Preview {
+    pub id: String,
+    pub image: String,
+}
 
-                println!("stdout: {}", stdout_str);
-                Ok(output_path.to_string())
-            }
-            Err(e) => return Err(format!("Failed to execute capture command: {}", e)),
-        };
-    }
+/// A print file rendered for a preview, waiting for the guest to approve it.
+#[derive(Clone)]
+struct RenderedPrint {
+    path: PathBuf,
+    layout: Layout,
+    profile: PrinterProfile,
+    session_id: Option<String>,
+}
 
-    #[cfg(not(target_os = "linux"))]
-    {
-        let sample_path = "sample.jpg"; // Replace with the actual path of your sample image
+/// What a print is composited from.
+struct Strip<'a> {
+    images: Vec<String>,
+    /// Capture session whose selected takes replace `images`.
+    session_id: Option<&'a str>,
+    color_mode: &'a str,
+    layout: &'a Layout,
+    /// Text in the band under the photos; the config's branding, or
+    /// [`DEFAULT_BRANDING`], when unset.
+    branding: Option<&'a str>,
+}
 
-        return match std::fs::copy(sample_path, output_path) {
-            Ok(_) => {
-                println!("Sample image copied to: {}", output_path);
-                Ok(output_path.to_string())
-            }
-            Err(e) => Err(format!("Failed to copy sample image: {}", e)),
-        };
-    }
+/// The pixel area a layout renders into.
+#[derive(Clone, Copy)]
+struct RenderArea {
+    width: u32,
+    height: u32,
+    dpi: f32,
+    border_px: u32,
 }
 
 #[tauri::command(async)]
 pub async fn print(
-    _app: AppHandle,
+    app: AppHandle,
     images: Vec<String>,
     output_path: &str,
     color_mode: &str,
     copies: usize,
     layout: Layout,
-) -> Result<(), String> {
-    let bg_color = if color_mode == "B&W" {
-        Rgba([255, 255, 255, 255])
-    } else {
-        Rgba([0, 0, 0, 255])
-    };
-
-    let border_px = ((BORDER / 2.54) * DPI).round() as u32;
-
-    let canvas = match layout {
-        Layout::A => apply_layout_a(images, color_mode, bg_color, border_px)?,
-        Layout::B => apply_layout_b(images, color_mode, bg_color, border_px)?,
-        Layout::C => apply_layout_c(images, color_mode, bg_color, border_px)?,
+    session_id: Option<String>,
+) -> Result<(), Error> {
+    let started = Instant::now();
+    let strip = Strip {
+        images,
+        session_id: session_id.as_deref(),
+        color_mode,
+        layout: &layout,
+        branding: None,
     };
+    let result = render_and_submit(&app, strip, output_path, copies);
+    if result.is_ok() {
+        count_pages(&app, copies);
 
-    if let Err(e) = canvas.save(output_path) {
-        eprintln!("Failed to save image: {}", e);
-        return Err(format!("Failed to save image: {}", e));
+        if let Some(id) = &session_id {
+            session::finish(id);
+        }
     }
+    let attempts_left = ledger::settle_print(&app, &result);
+    logging::command("print", started, &result);
 
-    let mut canvas2 = RgbaImage::from_pixel(WIDTH, HEIGHT, bg_color);
+    result.map_err(|error| error.with_attempts_left(attempts_left))
+}
 
-    let strip = match image::open(output_path) {
-        Ok(img) => image::imageops::resize(
-            &img,
-            WIDTH - (2 * border_px),
-            HEIGHT - (2 * border_px),
-            image::imageops::FilterType::Lanczos3,
-        ),
-        Err(e) => {
-            eprintln!("Failed to open image {}: {}", output_path, e);
-            return Err(format!("Failed to open image {}: {}", output_path, e));
-        }
-    };
+fn render_and_submit(
+    app: &AppHandle,
+    strip: Strip,
+    output_path: &str,
+    copies: usize,
+) -> Result<(), Error> {
+    let layout = strip.layout;
 
-    if let Err(e) = canvas2.copy_from(&strip, border_px, border_px) {
-        eprintln!("Failed to copy final strip to canvas2: {}", e);
-        return Err(e.to_string());
-    }
+    let render_start = Instant::now();
+    let (mut canvas, profile) = render_print(app, strip)?;
+    let render_time = render_start.elapsed();
+
+    let color_start = Instant::now();
+    prepare_for_printer(&mut canvas, &profile).map_err(Error::Imaging)?;
+    let color_time = color_start.elapsed();
 
-    if let Err(e) = canvas2.save(output_path) {
-        eprintln!("Failed to save final image: {}", e);
-        return Err(format!("Failed to save image: {}", e));
+    let encode_start = Instant::now();
+    if let Err(e) = canvas.save(output_path) {
+        log::error!("Failed to save image: {}", e);
+        return Err(Error::Imaging(format!("Failed to save image: {}", e)));
     }
+    let encode_time = encode_start.elapsed();
 
-    let print_res = match layout {
-        Layout::A | Layout::B => Command::new("lp")
-            .arg("-o")
-            .arg("media=w288h432")
-            .arg("-o")
-            .arg("fit-to-page")
-            .arg("-n")
-            .arg(copies.to_string())
-            .arg(output_path)
-            .output(),
-        Layout::C => Command::new("lp")
-            .arg("-n")
-            .arg(copies.to_string())
-            .arg(output_path)
-            .output(),
-    };
+    let submit_start = Instant::now();
+    submit_print(&profile, layout, copies, output_path)?;
 
-    match print_res {
-        Ok(output) => {
-            if !output.status.success() {
-                eprintln!(
-                    "Failed to print: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                );
-                return Err(format!(
-                    "Failed to print: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to execute print command: {}", e);
-            return Err(format!("Failed to execute print command: {}", e));
-        }
-    }
+    log::info!(
+        "print timings: render {:?}, color {:?}, encode {:?}, submit {:?}",
+        render_time,
+        color_time,
+        encode_time,
+        submit_start.elapsed()
+    );
 
     Ok(())
 }
 
-fn apply_layout_a(
+#[tauri::command(async)]
+pub async fn render_preview(
+    app: AppHandle,
     images: Vec<String>,
     color_mode: &str,
-    bg_color: Rgba<u8>,
-    border_px: u32,
-) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
-    let branding_height = ((2f32 / 2.54) * DPI).round() as u32;
+    layout: Layout,
+    session_id: Option<String>,
+    branding: Option<String>,
+) -> Result<Preview, Error> {
+    let strip = Strip {
+        images,
+        session_id: session_id.as_deref(),
+        color_mode,
+        layout: &layout,
+        branding: branding.as_deref(),
+    };
+    let (mut canvas, profile) = render_print(&app, strip)?;
 
-    // 2 vertical photos
-    let available_height = HEIGHT - branding_height - (3 * border_px);
+    let scale = PREVIEW_HEIGHT as f32 / canvas.height() as f32;
+    let thumbnail = image::imageops::thumbnail(
+        &canvas,
+        (canvas.width() as f32 * scale).round() as u32,
+        PREVIEW_HEIGHT,
+    );
 
-    let cell_height = available_height / 2;
-    let cell_width = WIDTH - (2 * border_px);
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 85)
+        .encode_image(&DynamicImage::ImageRgba8(thumbnail).to_rgb8())
+        .map_err(|e| Error::Imaging(format!("Failed to encode preview: {}", e)))?;
 
-    let mut canvas = RgbaImage::from_pixel(WIDTH, HEIGHT, bg_color);
+    // The printable file is written now so approving the preview only has
+    // to submit it
+    prepare_for_printer(&mut canvas, &profile).map_err(Error::Imaging)?;
 
-    for (i, img_path) in images.iter().enumerate().take(2) {
-        let y_offset = border_px + (i as u32 * (cell_height + border_px));
+    let id = format!(
+        "{:x}-{}",
+        Utc::now().timestamp_millis(),
+        PREVIEW_COUNTER.fetch_add(1, Ordering::SeqCst)
+    );
+    let path = std::env::temp_dir().join(format!("memorabooth-preview-{}.png", id));
 
-        let photo = match image::open(img_path) {
-            Ok(img) => {
-                let (orig_w, orig_h) = img.dimensions();
+    canvas
+        .save(&path)
+        .map_err(|e| Error::Imaging(format!("Failed to save image: {}", e)))?;
 
-                let cell_aspect = cell_width as f32 / cell_height as f32;
+    let mut previews = PREVIEWS.lock().unwrap();
 
-                let img_aspect = orig_w as f32 / orig_h as f32;
+    if previews.len() >= PREVIEW_CAPACITY {
+        if let Some((_, stale)) = previews.pop_front() {
+            let _ = fs::remove_file(stale.path);
+        }
+    }
 
-                let (crop_x, crop_y, crop_w, crop_h) = if img_aspect > cell_aspect {
-                    let new_w = (orig_h as f32 * cell_aspect).round() as u32;
+    previews.push_back((
+        id.clone(),
+        RenderedPrint {
+            path,
+            layout,
+            profile,
+            session_id,
+        },
+    ));
+
+    Ok(Preview {
+        id,
+        image: format!("data:image/jpeg;base64,{}", BASE64_STANDARD.encode(jpeg)),
+    })
+}
 
-                    let x = (orig_w - new_w) / 2;
+#[tauri::command(async)]
+pub async fn print_rendered(
+    app: AppHandle,
+    preview_id: String,
+    copies: usize,
+) -> Result<(), Error> {
+    let started = Instant::now();
+    let rendered = PREVIEWS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(id, _)| *id == preview_id)
+        .map(|(_, rendered)| rendered.clone())
+        .ok_or_else(|| {
+            Error::ImageMissing(format!("Preview {} is no longer available", preview_id))
+        })?;
+
+    let result = submit_print(
+        &rendered.profile,
+        &rendered.layout,
+        copies,
+        &rendered.path.to_string_lossy(),
+    );
+    if result.is_ok() {
+        count_pages(&app, copies);
+        // The strip went out, so the same preview can't be printed twice
+        discard_preview(&preview_id);
 
-                    (x, 0, new_w, orig_h)
-                } else {
-                    let new_h = (orig_w as f32 / cell_aspect).round() as u32;
+        if let Some(id) = &rendered.session_id {
+            session::finish(id);
+        }
+    }
+    let attempts_left = ledger::settle_print(&app, &result);
+    logging::command("print_rendered", started, &result);
 
-                    let y = (orig_h - new_h) / 2;
+    result.map_err(|error| error.with_attempts_left(attempts_left))
+}
 
-                    (0, y, orig_w, new_h)
-                };
+/// Forgets preview `id` and deletes its printable file.
+fn discard_preview(id: &str) {
+    let mut previews = PREVIEWS.lock().unwrap();
 
-                let cropped =
-                    image::imageops::crop_imm(&img, crop_x, crop_y, crop_w, crop_h).to_image();
+    if let Some(index) = previews.iter().position(|(preview, _)| preview == id) {
+        if let Some((_, rendered)) = previews.remove(index) {
+            let _ = fs::remove_file(rendered.path);
+        }
+    }
+}
 
-                let mut resized = image::imageops::resize(
-                    &cropped,
-                    cell_width,
-                    cell_height,
-                    image::imageops::FilterType::Lanczos3,
-                );
+/// Prints a calibration page through the normal compositor, with every slot
+/// of `layout` filled by a grey ramp and colour patches.
+#[tauri::command(async)]
+pub async fn print_test_page(app: AppHandle, layout: Layout, token: String) -> Result<(), Error> {
+    auth::require(&token)?;
+
+    let directory = app
+        .path()
+        .document_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let profile = config::active_printer(&directory);
+
+    let pattern_path = std::env::temp_dir().join("memorabooth-test-pattern.png");
+    test_pattern()
+        .save(&pattern_path)
+        .map_err(|e| Error::Imaging(format!("Failed to save test pattern: {}", e)))?;
+
+    let pattern = pattern_path.to_string_lossy().to_string();
+    photos::forget(&pattern);
+
+    let mut canvas = compose(
+        vec![pattern; layout.slots()],
+        "COLOR",
+        Rgba([255, 255, 255, 255]),
+        &layout,
+        false,
+        &profile,
+        DEFAULT_BRANDING,
+    )
+    .map_err(Error::Imaging)?;
+    prepare_for_printer(&mut canvas, &profile).map_err(Error::Imaging)?;
+
+    let path = std::env::temp_dir().join("memorabooth-test-page.png");
+    canvas
+        .save(&path)
+        .map_err(|e| Error::Imaging(format!("Failed to save image: {}", e)))?;
+
+    submit_print(&profile, &layout, 1, &path.to_string_lossy())
+}
 
-                if color_mode == "B&W" {
-                    for pixel in resized.pixels_mut() {
-                        let [r, g, b, a] = pixel.0;
+/// An 11-step grey ramp over primary and secondary colour patches, for
+/// checking tone and colour on paper.
+fn test_pattern() -> RgbaImage {
+    const WIDTH: u32 = 1800;
+    const HEIGHT: u32 = 1200;
+    const STEPS: u32 = 11;
+    const PATCHES: [[u8; 3]; 8] = [
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [0, 255, 255],
+        [255, 0, 255],
+        [255, 255, 0],
+        [0, 0, 0],
+        [255, 255, 255],
+    ];
+
+    RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        if y < HEIGHT / 2 {
+            let step = (x * STEPS / WIDTH).min(STEPS - 1);
+            let level = (step * 255 / (STEPS - 1)) as u8;
+            Rgba([level, level, level, 255])
+        } else {
+            let [r, g, b] = PATCHES[(x * PATCHES.len() as u32 / WIDTH) as usize];
+            Rgba([r, g, b, 255])
+        }
+    })
+}
 
-                        let gray = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+/// Composites `strip` for the active printer, still in sRGB.
+fn render_print(app: &AppHandle, strip: Strip) -> Result<(RgbaImage, PrinterProfile), Error> {
+    let Strip {
+        images,
+        session_id,
+        color_mode,
+        layout,
+        branding,
+    } = strip;
+
+    let images = match session_id {
+        Some(id) => session::selected_images(id, layout)?,
+        None => images,
+    };
+    if images.len() < layout.slots() {
+        return Err(Error::ImageMissing(format!(
+            "Layout {:?} needs {} photos, got {}",
+            layout,
+            layout.slots(),
+            images.len()
+        )));
+    }
+    if let Some(missing) = images.iter().find(|path| !Path::new(path).exists()) {
+        return Err(Error::ImageMissing(format!(
+            "Photo {} does not exist",
+            missing
+        )));
+    }
 
-                        *pixel = Rgba([gray, gray, gray, a]);
-                    }
-                }
+    let bg_color = if color_mode == "B&W" {
+        Rgba([255, 255, 255, 255])
+    } else {
+        Rgba([0, 0, 0, 255])
+    };
 
-                resized
-            }
-            Err(e) => {
-                eprintln!("Failed to open image {}: {}", img_path, e);
+    let directory = app
+        .path()
+        .document_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let config = config::load_config(&directory);
+    let smart_crop = config.as_ref().is_some_and(|config| config.smart_crop);
+    let branding = branding
+        .or_else(|| {
+            config
+                .as_ref()
+                .and_then(|config| config.branding.as_deref())
+        })
+        .unwrap_or(DEFAULT_BRANDING);
+    check_branding(branding).map_err(Error::Invalid)?;
+    let profile = config::active_printer(&directory);
 
-                return Err(format!("Failed to open image {}: {}", img_path, e));
-            }
-        };
+    let canvas = compose(
+        images, color_mode, bg_color, layout, smart_crop, &profile, branding,
+    )
+    .map_err(Error::Imaging)?;
 
-        if let Err(e) = canvas.copy_from(&photo, border_px, y_offset) {
-            eprintln!("photo error: {}", e);
+    Ok((canvas, profile))
+}
 
-            return Err(format!("photo error: {}", e));
-        }
+/// Checks that `branding` fits under the photos at a readable size.
+pub fn check_branding(branding: &str) -> Result<(), String> {
+    if branding.chars().count() > MAX_BRANDING {
+        return Err(format!(
+            "Branding can be at most {} characters",
+            MAX_BRANDING
+        ));
     }
 
-    let label = "memora.".to_string();
+    Ok(())
+}
 
-    let label_font_src = include_bytes!("../fonts/Burgundia.otf");
+/// Adds a print to the page counter, which holds at the pack size until the
+/// paper is refilled.
+fn count_pages(app: &AppHandle, copies: usize) {
+    let directory = app
+        .path()
+        .document_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if let Err(e) = config::count_pages(&directory, copies as u64) {
+        log::error!("Failed to count printed pages: {}", e);
+    }
+}
 
-    let label_font = FontArc::try_from_slice(label_font_src as &[u8]).expect("Failed to load font");
+/// Converts a composite into the printer's colour space, if it has a profile.
+fn prepare_for_printer(canvas: &mut RgbaImage, profile: &PrinterProfile) -> Result<(), String> {
+    if let Some(icc_path) = &profile.icc_profile {
+        let icc = fs::read(icc_path)
+            .map_err(|e| format!("Failed to read ICC profile {}: {}", icc_path, e))?;
 
-    let label_scale = font_scale_for_height(&label_font, branding_height as f32 * 0.6);
+        color::to_printer_space(canvas, &icc, profile.rendering_intent)?;
+    }
 
-    let txt_color = if color_mode == "B&W" {
-        Rgba([0, 0, 0, 255])
+    Ok(())
+}
+
+fn submit_print(
+    profile: &PrinterProfile,
+    layout: &Layout,
+    copies: usize,
+    path: &str,
+) -> Result<(), Error> {
+    let mut cmd = Command::new("lp");
+
+    if let Some(queue) = &profile.queue {
+        cmd.arg("-d").arg(queue);
+    }
+
+    if *layout == Layout::C && profile.cuts_strips {
+        if let Some(media) = &profile.strip_media {
+            cmd.arg("-o").arg(format!("media={}", media));
+        }
     } else {
-        Rgba([255, 255, 255, 255])
-    };
+        cmd.arg("-o")
+            .arg(format!("media={}", profile.media))
+            .arg("-o")
+            .arg("fit-to-page");
+    }
 
-    let label_width: f32 = label
-        .chars()
-        .map(|c| {
-            let glyph_id = label_font.glyph_id(c);
+    let print_res = cmd.arg("-n").arg(copies.to_string()).arg(path).output();
 
-            label_font.as_scaled(label_scale.y).h_advance(glyph_id)
-        })
-        .sum();
+    let detail = match print_res {
+        Ok(output) if output.status.success() => return Ok(()),
+        Ok(output) => format!(
+            "Failed to print: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(e) => format!("Failed to execute print command: {}", e),
+    };
+    log::error!("{}", detail);
 
-    let label_x = ((WIDTH as f32 - label_width) / 2.0) as i32;
+    // Tell a queue that is down apart from one that rejected this job
+    if printer::status(profile).online {
+        Err(Error::Print(detail))
+    } else {
+        Err(Error::PrinterOffline(detail))
+    }
+}
 
-    let scaled_font = label_font.as_scaled(label_scale.y);
-    let text_visual_height = scaled_font.ascent() - scaled_font.descent();
+/// Renders the layout straight into the inner area of the final bordered
+/// canvas for `profile`'s paper, so the print only needs to be encoded once.
+fn compose(
+    images: Vec<String>,
+    color_mode: &str,
+    bg_color: Rgba<u8>,
+    layout: &Layout,
+    smart_crop: bool,
+    profile: &PrinterProfile,
+    branding: &str,
+) -> Result<RgbaImage, String> {
+    check_profile(profile)?;
+    let (area, offset) = render_area(profile)?;
+
+    let apply_layout = match layout {
+        Layout::A => apply_layout_a,
+        Layout::B => apply_layout_b,
+        Layout::C => apply_layout_c,
+    };
 
-    let branding_start_y = HEIGHT - branding_height;
-    let vertical_padding = (branding_height as f32 - text_visual_height) / 2.0;
+    let strip = apply_layout(images, color_mode, bg_color, &area, smart_crop, branding)?;
 
-    let label_y = (branding_start_y as f32 + vertical_padding) as i32;
+    let mut canvas =
+        RgbaImage::from_pixel(area.width + 2 * offset, area.height + 2 * offset, bg_color);
 
-    draw_text_mut(
-        &mut canvas,
-        txt_color,
-        label_x,
-        label_y,
-        label_scale,
-        &label_font,
-        &label,
-    );
+    if let Err(e) = canvas.copy_from(&strip, offset, offset) {
+        log::error!("Failed to copy final strip to canvas: {}", e);
+        return Err(e.to_string());
+    }
 
     Ok(canvas)
 }
 
-fn apply_layout_b(
+/// Checks that `profile` has sane sizes and paper every layout fits on, so
+/// a bad profile is turned away when it is saved rather than at print time.
+pub fn check_profile(profile: &PrinterProfile) -> Result<(), String> {
+    let within = |value: f32, min: f32, max: f32| value.is_finite() && value >= min && value <= max;
+
+    if !within(profile.dpi, f32::MIN_POSITIVE, MAX_DPI) {
+        return Err(format!(
+            "Printer profile {} needs a DPI above 0 and at most {}",
+            profile.name, MAX_DPI
+        ));
+    }
+    if !within(profile.paper_width, f32::MIN_POSITIVE, MAX_PAPER)
+        || !within(profile.paper_height, f32::MIN_POSITIVE, MAX_PAPER)
+    {
+        return Err(format!(
+            "Printer profile {} needs a paper size above 0 and at most {} inches",
+            profile.name, MAX_PAPER
+        ));
+    }
+    if profile.paper_capacity == 0 {
+        return Err(format!(
+            "Printer profile {} needs a paper capacity of at least 1 print",
+            profile.name
+        ));
+    }
+    let max_edge = profile.paper_width.min(profile.paper_height) * MAX_EDGE_SHARE;
+    if !within(profile.margin, 0.0, max_edge) || !within(profile.bleed, 0.0, max_edge) {
+        return Err(format!(
+            "Printer profile {} needs a margin and bleed between 0 and {} inches",
+            profile.name, max_edge
+        ));
+    }
+    let longest = profile.paper_width.max(profile.paper_height) + 2.0 * profile.bleed;
+    if longest * profile.dpi > MAX_CANVAS_SIDE {
+        return Err(format!(
+            "Printer profile {} would render {} pixels across, more than {}; lower its DPI",
+            profile.name,
+            (longest * profile.dpi).round(),
+            MAX_CANVAS_SIDE
+        ));
+    }
+
+    let (area, _) = render_area(profile)?;
+    for layout in [Layout::A, Layout::B, Layout::C] {
+        cell_size(&layout, &area)
+            .map_err(|e| format!("Printer profile {}: {}", profile.name, e))?;
+    }
+
+    Ok(())
+}
+
+/// The area of `profile`'s canvas that layouts render into, inside its
+/// bleed, margin and border, and that area's offset from the canvas edge.
+fn render_area(profile: &PrinterProfile) -> Result<(RenderArea, u32), String> {
+    let dpi = profile.dpi;
+
+    let width = ((profile.paper_width + 2.0 * profile.bleed) * dpi).round() as u32;
+    let height = ((profile.paper_height + 2.0 * profile.bleed) * dpi).round() as u32;
+
+    let border_px = ((BORDER / 2.54) * dpi).round() as u32;
+    let offset = border_px + ((profile.bleed + profile.margin) * dpi).round() as u32;
+
+    match (
+        width.checked_sub(2 * offset),
+        height.checked_sub(2 * offset),
+    ) {
+        (Some(inner_width), Some(inner_height)) => Ok((
+            RenderArea {
+                width: inner_width,
+                height: inner_height,
+                dpi,
+                border_px,
+            },
+            offset,
+        )),
+        _ => Err(format!(
+            "Printer profile {} leaves no printable area",
+            profile.name
+        )),
+    }
+}
+
+/// Height of the branding band under the photos of `layout`.
+fn branding_px(layout: &Layout, dpi: f32) -> u32 {
+    let cm = match layout {
+        Layout::A => 2.0,
+        Layout::B | Layout::C => 1.0,
+    };
+
+    ((cm / 2.54) * dpi).round() as u32
+}
+
+/// Width and height of each photo cell of `layout` in `area`, or an error
+/// when the paper is too small for its borders and branding.
+fn cell_size(layout: &Layout, area: &RenderArea) -> Result<(u32, u32), String> {
+    let border_px = area.border_px;
+    let branding = branding_px(layout, area.dpi);
+
+    // Room taken by borders and branding, and how many cells share the rest
+    let (width, height, used_width, used_height, columns, rows) = match layout {
+        Layout::A => (
+            area.width,
+            area.height,
+            2 * border_px,
+            branding + 3 * border_px,
+            1,
+            2,
+        ),
+        // Laid out in landscape
+        Layout::B => (
+            area.height,
+            area.width,
+            3 * border_px,
+            branding + 2 * border_px,
+            2,
+            2,
+        ),
+        // Two strips with a double border between them
+        Layout::C => (
+            area.width,
+            area.height,
+            4 * border_px,
+            branding + 4 * border_px,
+            2,
+            4,
+        ),
+    };
+
+    match (
+        width.checked_sub(used_width),
+        height.checked_sub(used_height),
+    ) {
+        (Some(free_width), Some(free_height)) if free_width >= columns && free_height >= rows => {
+            Ok((free_width / columns, free_height / rows))
+        }
+        _ => Err(format!("The paper is too small for Layout {:?}", layout)),
+    }
+}
+
+fn apply_layout_a(
     images: Vec<String>,
     color_mode: &str,
     bg_color: Rgba<u8>,
-    border_px: u32,
+    area: &RenderArea,
+    smart_crop: bool,
+    branding: &str,
 ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
-    // Internally work in landscape orientation (6x4)
-    let landscape_width = HEIGHT;
-    let landscape_height = WIDTH;
+    let RenderArea {
+        width,
+        height,
+        dpi,
+        border_px,
+    } = *area;
 
-    let branding_height = ((1f32 / 2.54) * DPI).round() as u32;
+    let branding_height = branding_px(&Layout::A, dpi);
 
-    let available_height = landscape_height - branding_height - (2 * border_px);
+    // 2 vertical photos
+    let (cell_width, cell_height) = cell_size(&Layout::A, area)?;
 
-    let cell_width = (landscape_width - (3 * border_px)) / 2;
-    let cell_height = available_height / 2;
+    let mut canvas = RgbaImage::from_pixel(width, height, bg_color);
 
-    let mut canvas = RgbaImage::from_pixel(landscape_width, landscape_height, bg_color);
+    let photos = photos::load_all(
+        &images[..images.len().min(2)],
+        Some((cell_width, cell_height)),
+    )?;
+    let cells: Vec<RgbaImage> = photos
+        .par_iter()
+        .map(|img| fit_to_cell(img, cell_width, cell_height, color_mode, smart_crop))
+        .collect();
 
-    for (i, img_path) in images.iter().enumerate().take(4) {
-        let y_offset = border_px + (i as u32 / 2) * (cell_height + border_px);
-        let x_offset = border_px + (i as u32 % 2) * (cell_width + border_px);
+    for (i, photo) in cells.iter().enumerate() {
+        let y_offset = border_px + (i as u32 * (cell_height + border_px));
+
+        if let Err(e) = canvas.copy_from(photo, border_px, y_offset) {
+            log::error!("photo error: {}", e);
 
-        let photo = match image::open(img_path) {
-            Ok(img) => {
-                let (orig_w, orig_h) = img.dimensions();
-
-                let cell_aspect = cell_width as f32 / cell_height as f32;
-                let img_aspect = orig_w as f32 / orig_h as f32;
-
-                let (crop_x, crop_y, crop_w, crop_h) = if img_aspect > cell_aspect {
-                    let new_w = (orig_h as f32 * cell_aspect).round() as u32;
-                    let x = (orig_w - new_w) / 2;
-                    (x, 0, new_w, orig_h)
-                } else {
-                    let new_h = (orig_w as f32 / cell_aspect).round() as u32;
-                    let y = (orig_h - new_h) / 2;
-                    (0, y, orig_w, new_h)
-                };
-
-                let cropped =
-                    image::imageops::crop_imm(&img, crop_x, crop_y, crop_w, crop_h).to_image();
-
-                let mut resized = image::imageops::resize(
-                    &cropped,
-                    cell_width,
-                    cell_height,
-                    image::imageops::FilterType::Lanczos3,
-                );
-
-                if color_mode == "B&W" {
-                    for pixel in resized.pixels_mut() {
-                        let [r, g, b, a] = pixel.0;
-
-                        let gray = ((r as u32 + g as u32 + b as u32) / 3) as u8;
-
-                        *pixel = Rgba([gray, gray, gray, a]);
-                    }
-                }
-
-                resized
-            }
-            Err(e) => {
-                eprintln!("Failed to open image {}: {}", img_path, e);
-                return Err(format!("Failed to open image {}: {}", img_path, e));
-            }
-        };
-
-        if let Err(e) = canvas.copy_from(&photo, x_offset, y_offset) {
-            eprintln!("photo error: {}", e);
             return Err(format!("photo error: {}", e));
         }
     }
 
-    let label = "memora.".to_string();
-
-    let label_font_src = include_bytes!("../fonts/Burgundia.otf");
+    draw_label(
+        &mut canvas,
+        branding,
+        color_mode,
+        (0, width),
+        width.saturating_sub(2 * border_px),
+        branding_height,
+        0.6,
+    )?;
 
-    let label_font = FontArc::try_from_slice(label_font_src as &[u8]).expect("Failed to load font");
+    Ok(canvas)
+}
 
-    let label_scale = font_scale_for_height(&label_font, branding_height as f32 * 0.8);
+fn apply_layout_b(
+    images: Vec<String>,
+    color_mode: &str,
+    bg_color: Rgba<u8>,
+    area: &RenderArea,
+    smart_crop: bool,
+    branding: &str,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let RenderArea {
+        width,
+        height,
+        dpi,
+        border_px,
+    } = *area;
 
-    let txt_color = if color_mode == "B&W" {
-        Rgba([0, 0, 0, 255])
-    } else {
-        Rgba([255, 255, 255, 255])
-    };
+    // Internally work in landscape orientation (6x4)
+    let landscape_width = height;
+    let landscape_height = width;
 
-    let label_width: f32 = label
-        .chars()
-        .map(|c| {
-            let glyph_id = label_font.glyph_id(c);
+    let branding_height = branding_px(&Layout::B, dpi);
 
-            label_font.as_scaled(label_scale.y).h_advance(glyph_id)
-        })
-        .sum();
+    let (cell_width, cell_height) = cell_size(&Layout::B, area)?;
 
-    let label_x = ((landscape_width as f32 - label_width) / 2.0) as i32;
+    let mut canvas = RgbaImage::from_pixel(landscape_width, landscape_height, bg_color);
 
-    let scaled_font = label_font.as_scaled(label_scale.y);
-    let text_visual_height = scaled_font.ascent() - scaled_font.descent();
+    let photos = photos::load_all(
+        &images[..images.len().min(4)],
+        Some((cell_width, cell_height)),
+    )?;
+    let cells: Vec<RgbaImage> = photos
+        .par_iter()
+        .map(|img| fit_to_cell(img, cell_width, cell_height, color_mode, smart_crop))
+        .collect();
 
-    let branding_start_y = landscape_height - branding_height;
-    let vertical_padding = (branding_height as f32 - text_visual_height) / 2.0;
+    for (i, photo) in cells.iter().enumerate() {
+        let y_offset = border_px + (i as u32 / 2) * (cell_height + border_px);
+        let x_offset = border_px + (i as u32 % 2) * (cell_width + border_px);
 
-    let label_y = (branding_start_y as f32 + vertical_padding) as i32;
+        if let Err(e) = canvas.copy_from(photo, x_offset, y_offset) {
+            log::error!("photo error: {}", e);
+            return Err(format!("photo error: {}", e));
+        }
+    }
 
-    draw_text_mut(
+    draw_label(
         &mut canvas,
-        txt_color,
-        label_x,
-        label_y,
-        label_scale,
-        &label_font,
-        &label,
-    );
+        branding,
+        color_mode,
+        (0, landscape_width),
+        landscape_width.saturating_sub(2 * border_px),
+        branding_height,
+        0.8,
+    )?;
 
     // Rotate back into portrait 4x6
     let rotated = image::imageops::rotate90(&canvas);
@@ -425,128 +797,147 @@ fn apply_layout_c(
     images: Vec<String>,
     color_mode: &str,
     bg_color: Rgba<u8>,
-    border_px: u32,
+    area: &RenderArea,
+    smart_crop: bool,
+    branding: &str,
 ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, String> {
+    let RenderArea {
+        width,
+        height,
+        dpi,
+        border_px,
+    } = *area;
+
     let center_gap = border_px * 2;
-    let branding_height = ((1.0f32 / 2.54) * DPI).round() as u32;
+    let branding_height = branding_px(&Layout::C, dpi);
 
-    let available_height = HEIGHT - branding_height - (4 * border_px);
-    let cell_width = (WIDTH - (2 * border_px) - center_gap) / 2;
-    let cell_height = available_height / 4;
+    let (cell_width, cell_height) = cell_size(&Layout::C, area)?;
 
-    let mut canvas = RgbaImage::from_pixel(WIDTH, HEIGHT, bg_color);
+    let mut canvas = RgbaImage::from_pixel(width, height, bg_color);
 
-    for (i, img_path) in images.iter().enumerate().take(4) {
-        let y_offset = border_px + i as u32 * (cell_height + border_px);
+    let photos = photos::load_all(
+        &images[..images.len().min(4)],
+        Some((cell_width, cell_height)),
+    )?;
+    let cells: Vec<RgbaImage> = photos
+        .par_iter()
+        .map(|img| fit_to_cell(img, cell_width, cell_height, color_mode, smart_crop))
+        .collect();
 
-        let photo = match image::open(img_path) {
-            Ok(img) => {
-                let (orig_w, orig_h) = img.dimensions();
-                let cell_aspect = cell_width as f32 / cell_height as f32;
-                let img_aspect = orig_w as f32 / orig_h as f32;
-
-                let (crop_x, crop_y, crop_w, crop_h) = if img_aspect > cell_aspect {
-                    let new_w = (orig_h as f32 * cell_aspect).round() as u32;
-                    let x = (orig_w - new_w) / 2;
-                    (x, 0, new_w, orig_h)
-                } else {
-                    let new_h = (orig_w as f32 / cell_aspect).round() as u32;
-                    let y = (orig_h - new_h) / 2;
-                    (0, y, orig_w, new_h)
-                };
-
-                let cropped =
-                    image::imageops::crop_imm(&img, crop_x, crop_y, crop_w, crop_h).to_image();
-
-                let mut resized = image::imageops::resize(
-                    &cropped,
-                    cell_width,
-                    cell_height,
-                    image::imageops::FilterType::Lanczos3,
-                );
-
-                if color_mode == "B&W" {
-                    for pixel in resized.pixels_mut() {
-                        let [r, g, b, a] = pixel.0;
-
-                        let gray = ((r as u32 + g as u32 + b as u32) / 3) as u8;
-
-                        *pixel = Rgba([gray, gray, gray, a]);
-                    }
-                }
-
-                resized
-            }
-            Err(e) => {
-                eprintln!("Failed to open image {}: {}", img_path, e);
-                return Err(format!("Failed to open image {}: {}", img_path, e));
-            }
-        };
+    for (i, photo) in cells.iter().enumerate() {
+        let y_offset = border_px + i as u32 * (cell_height + border_px);
 
         let left_x = border_px;
         let right_x = border_px + cell_width + center_gap;
 
-        if let Err(e) = canvas.copy_from(&photo, left_x, y_offset) {
-            eprintln!("Left photo error: {}", e);
+        if let Err(e) = canvas.copy_from(photo, left_x, y_offset) {
+            log::error!("Left photo error: {}", e);
             return Err(format!("Left photo error: {}", e));
         }
 
-        if let Err(e) = canvas.copy_from(&photo, right_x, y_offset) {
-            eprintln!("Right photo error: {}", e);
+        if let Err(e) = canvas.copy_from(photo, right_x, y_offset) {
+            log::error!("Right photo error: {}", e);
             return Err(format!("Right photo error: {}", e));
         }
     }
 
-    let label = "memora.".to_string();
-    let label_font_src = include_bytes!("../fonts/Burgundia.otf");
-    let label_font = FontArc::try_from_slice(label_font_src as &[u8]).expect("Failed to load font");
+    // Both strips carry the branding under their own column
+    for left in [0, cell_width + center_gap] {
+        draw_label(
+            &mut canvas,
+            branding,
+            color_mode,
+            (left, cell_width),
+            cell_width.saturating_sub(2 * border_px),
+            branding_height,
+            0.6,
+        )?;
+    }
 
-    let label_scale = font_scale_for_height(&label_font, branding_height as f32 * 0.6);
+    Ok(canvas)
+}
 
-    let txt_color = if color_mode == "B&W" {
-        Rgba([0, 0, 0, 255])
+/// Crops `img` to the cell's aspect ratio and scales it to fill the cell.
+fn fit_to_cell(
+    img: &DynamicImage,
+    cell_width: u32,
+    cell_height: u32,
+    color_mode: &str,
+    smart_crop: bool,
+) -> RgbaImage {
+    let cell_aspect = cell_width as f32 / cell_height as f32;
+    let (crop_x, crop_y, crop_w, crop_h) = crop_window(img, cell_aspect, smart_crop);
+
+    let cropped = image::imageops::crop_imm(img, crop_x, crop_y, crop_w, crop_h);
+
+    let mut resized = image::imageops::resize(
+        &*cropped,
+        cell_width,
+        cell_height,
+        image::imageops::FilterType::Lanczos3,
+    );
+
+    if color_mode == "B&W" {
+        for pixel in resized.pixels_mut() {
+            let [r, g, b, a] = pixel.0;
+
+            let gray = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+
+            *pixel = Rgba([gray, gray, gray, a]);
+        }
+    }
+
+    resized
+}
+
+/// Picks the largest window with `cell_aspect` inside `img`. It is centered,
+/// unless `smart_crop` finds faces, in which case it is centered on them.
+fn crop_window(img: &DynamicImage, cell_aspect: f32, smart_crop: bool) -> (u32, u32, u32, u32) {
+    let faces = if smart_crop {
+        faces::detect(img)
     } else {
-        Rgba([255, 255, 255, 255])
+        vec![]
     };
 
-    let label_width: f32 = label
-        .chars()
-        .map(|c| {
-            let glyph_id = label_font.glyph_id(c);
-            label_font.as_scaled(label_scale.y).h_advance(glyph_id)
-        })
-        .sum();
-
-    let label_x = ((cell_width as f32 - label_width) / 2.0) as i32;
+    window_around(img.dimensions(), cell_aspect, &faces)
+}
 
-    let scaled_font = label_font.as_scaled(label_scale.y);
-    let text_visual_height = scaled_font.ascent() - scaled_font.descent();
+/// The window of [`crop_window`] for an image of `(orig_w, orig_h)` with
+/// `faces` already found, kept inside the image.
+fn window_around(
+    (orig_w, orig_h): (u32, u32),
+    cell_aspect: f32,
+    faces: &[faces::FaceBox],
+) -> (u32, u32, u32, u32) {
+    let img_aspect = orig_w as f32 / orig_h as f32;
+
+    let (crop_w, crop_h) = if img_aspect > cell_aspect {
+        ((orig_h as f32 * cell_aspect).round() as u32, orig_h)
+    } else {
+        (orig_w, (orig_w as f32 / cell_aspect).round() as u32)
+    };
 
-    let branding_start_y = HEIGHT - branding_height;
-    let vertical_padding = (branding_height as f32 - text_visual_height) / 2.0;
+    if faces.is_empty() {
+        return ((orig_w - crop_w) / 2, (orig_h - crop_h) / 2, crop_w, crop_h);
+    }
 
-    let label_y = (branding_start_y as f32 + vertical_padding) as i32;
+    let left = faces.iter().map(|f| f.left).min().unwrap_or(0);
+    let right = faces.iter().map(|f| f.right).max().unwrap_or(orig_w);
+    let top = faces.iter().map(|f| f.top).min().unwrap_or(0);
+    let bottom = faces.iter().map(|f| f.bottom).max().unwrap_or(orig_h);
 
-    draw_text_mut(
-        &mut canvas,
-        txt_color,
-        label_x,
-        label_y,
-        label_scale,
-        &label_font,
-        &label,
-    );
-    draw_text_mut(
-        &mut canvas,
-        txt_color,
-        label_x + (cell_width + center_gap) as i32,
-        label_y,
-        label_scale,
-        &label_font,
-        &label,
-    );
+    let center_on = |start: u32, end: u32, window: u32, total: u32| {
+        ((start + end) / 2)
+            .saturating_sub(window / 2)
+            .min(total - window)
+    };
 
-    Ok(canvas)
+    (
+        center_on(left, right, crop_w, orig_w),
+        center_on(top, bottom, crop_h, orig_h),
+        crop_w,
+        crop_h,
+    )
 }
 
 fn font_scale_for_height(font: &FontArc, target_height: f32) -> PxScale {
@@ -560,14 +951,212 @@ fn font_scale_for_height(font: &FontArc, target_height: f32) -> PxScale {
     }
 }
 
+/// Scale for `label` at `target_height`, shrunk to fit `max_width`.
+fn fit_label_scale(font: &FontArc, label: &str, target_height: f32, max_width: f32) -> PxScale {
+    let scale = font_scale_for_height(font, target_height);
+    let width: f32 = label
+        .chars()
+        .map(|c| font.as_scaled(scale.y).h_advance(font.glyph_id(c)))
+        .sum();
+
+    if width > max_width && width > 0.0 {
+        font_scale_for_height(font, target_height * max_width / width)
+    } else {
+        scale
+    }
+}
+
+/// Draws `label` centred across the `span` pixels from `left`, in the
+/// branding band of `band_height` at the bottom of `canvas`. It is `fill` of
+/// the band high, shrunk to fit `max_width`.
+fn draw_label(
+    canvas: &mut RgbaImage,
+    label: &str,
+    color_mode: &str,
+    (left, span): (u32, u32),
+    max_width: u32,
+    band_height: u32,
+    fill: f32,
+) -> Result<(), String> {
+    let font = FontArc::try_from_slice(LABEL_FONT)
+        .map_err(|e| format!("Failed to load the label font: {}", e))?;
+    let scale = fit_label_scale(&font, label, band_height as f32 * fill, max_width as f32);
+    let scaled_font = font.as_scaled(scale.y);
+
+    let txt_color = if color_mode == "B&W" {
+        Rgba([0, 0, 0, 255])
+    } else {
+        Rgba([255, 255, 255, 255])
+    };
+
+    let label_width: f32 = label
+        .chars()
+        .map(|c| scaled_font.h_advance(font.glyph_id(c)))
+        .sum();
+    let label_x = left as i32 + ((span as f32 - label_width) / 2.0) as i32;
+
+    let text_visual_height = scaled_font.ascent() - scaled_font.descent();
+    let branding_start_y = canvas.height() - band_height;
+    let vertical_padding = (band_height as f32 - text_visual_height) / 2.0;
+    let label_y = (branding_start_y as f32 + vertical_padding) as i32;
+
+    draw_text_mut(canvas, txt_color, label_x, label_y, scale, &font, label);
+
+    Ok(())
+}
+
 fn _get_asset_path(app_handle: &AppHandle, filename: &str) -> Result<PathBuf, String> {
-    let resource_path = app_handle.path().resolve(
-        format!("assets/{}", filename),
-        tauri::path::BaseDirectory::Resource,
-    );
-    if let Err(e) = resource_path {
-        return Err(format!("Failed to find resource: {}", e));
+    app_handle
+        .path()
+        .resolve(
+            format!("assets/{}", filename),
+            tauri::path::BaseDirectory::Resource,
+        )
+        .map_err(|e| format!("Failed to find resource: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face(left: u32, top: u32, right: u32, bottom: u32) -> faces::FaceBox {
+        faces::FaceBox {
+            left,
+            top,
+            right,
+            bottom,
+        }
     }
 
-    Ok(resource_path.unwrap())
+    fn profile(change: impl FnOnce(&mut PrinterProfile)) -> PrinterProfile {
+        let mut profile = PrinterProfile::default();
+        change(&mut profile);
+        profile
+    }
+
+    #[test]
+    fn accepts_the_stock_profiles() {
+        assert_eq!(check_profile(&PrinterProfile::default()), Ok(()));
+        assert_eq!(
+            check_profile(&profile(|p| {
+                p.paper_width = 8.0;
+                p.paper_height = 10.0;
+                p.bleed = 0.05;
+                p.margin = 0.1;
+            })),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_canvases_too_large_to_allocate() {
+        let huge = profile(|p| {
+            p.paper_width = MAX_PAPER;
+            p.paper_height = MAX_PAPER;
+            p.dpi = MAX_DPI;
+        });
+        assert!(check_profile(&huge)
+            .unwrap_err()
+            .contains("57600 pixels across"));
+
+        // Bleed counts towards the canvas
+        assert!(check_profile(&profile(|p| {
+            p.dpi = 1900.0;
+            p.bleed = 0.2;
+        }))
+        .is_err());
+        assert_eq!(check_profile(&profile(|p| p.dpi = 1900.0)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_bleed_and_margin_past_a_share_of_the_paper() {
+        assert!(check_profile(&profile(|p| p.bleed = MAX_PAPER)).is_err());
+        assert!(check_profile(&profile(|p| p.margin = 1.5)).is_err());
+        assert!(check_profile(&profile(|p| p.margin = -0.1)).is_err());
+    }
+
+    #[test]
+    fn rejects_nonsense_sizes() {
+        assert!(check_profile(&profile(|p| p.dpi = 0.0)).is_err());
+        assert!(check_profile(&profile(|p| p.dpi = f32::NAN)).is_err());
+        assert!(check_profile(&profile(|p| p.paper_height = 0.3)).is_err());
+        assert!(check_profile(&profile(|p| p.paper_capacity = 0)).is_err());
+    }
+
+    #[test]
+    fn discarding_a_preview_forgets_it_and_deletes_its_file() {
+        let path = std::env::temp_dir().join("memorabooth-preview-discard.png");
+        fs::write(&path, b"png").unwrap();
+
+        PREVIEWS.lock().unwrap().push_back((
+            "discard".to_string(),
+            RenderedPrint {
+                path: path.clone(),
+                layout: Layout::A,
+                profile: PrinterProfile::default(),
+                session_id: None,
+            },
+        ));
+
+        discard_preview("discard");
+
+        assert!(!PREVIEWS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(id, _)| id == "discard"));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn crops_the_middle_without_faces() {
+        // A 4:3 photo in a square cell loses equal strips on both sides
+        assert_eq!(window_around((800, 600), 1.0, &[]), (100, 0, 600, 600));
+        // and in a wide cell, equal strips top and bottom
+        assert_eq!(window_around((800, 600), 2.0, &[]), (0, 100, 800, 400));
+        assert_eq!(window_around((600, 600), 1.0, &[]), (0, 0, 600, 600));
+    }
+
+    #[test]
+    fn centres_the_crop_on_the_faces() {
+        let crop = window_around((1000, 600), 1.0, &[face(300, 100, 400, 200)]);
+        assert_eq!(crop, (50, 0, 600, 600));
+
+        // Several faces are framed as one group
+        let group = [face(200, 100, 300, 200), face(600, 150, 700, 250)];
+        assert_eq!(window_around((1000, 600), 1.0, &group), (150, 0, 600, 600));
+    }
+
+    #[test]
+    fn keeps_the_crop_inside_the_photo() {
+        // Faces at either edge pull the window only as far as the border
+        assert_eq!(
+            window_around((1000, 600), 1.0, &[face(0, 0, 80, 80)]),
+            (0, 0, 600, 600)
+        );
+        assert_eq!(
+            window_around((1000, 600), 1.0, &[face(920, 500, 1000, 600)]),
+            (400, 0, 600, 600)
+        );
+        assert_eq!(
+            window_around((600, 1000), 1.0, &[face(250, 950, 350, 1000)]),
+            (0, 400, 600, 600)
+        );
+    }
+
+    #[test]
+    fn draws_the_label_inside_its_band() {
+        let background = Rgba([0, 0, 0, 255]);
+        let mut canvas = RgbaImage::from_pixel(600, 400, background);
+
+        draw_label(&mut canvas, "memora.", "COLOR", (300, 300), 280, 100, 0.6).unwrap();
+
+        let inked: Vec<(u32, u32)> = canvas
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| **pixel != background)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert!(!inked.is_empty());
+        assert!(inked.iter().all(|&(x, y)| x >= 300 && y >= 300));
+    }
 }
@@ -0,0 +1,466 @@
+use std::{collections::BTreeMap, sync::Mutex, time::Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{auth, cash, config, error::Error, logging, razorpay};
+
+const LEDGER_VERSION: u32 = 1;
+const LEDGER_FILE: &str = "ledger.json";
+/// Failed prints of one sale before it is refunded without waiting for the
+/// guest to give up.
+pub const MAX_PRINT_ATTEMPTS: u32 = 3;
+/// Refunds of one sale Razorpay may decline before it has to be refunded
+/// from the Razorpay dashboard instead.
+pub const MAX_REFUND_ATTEMPTS: u32 = 3;
+
+// Serialises read-modify-write cycles on the ledger file
+static LEDGER_LOCK: Mutex<()> = Mutex::new(());
+/// The paid sale whose photos are being taken and printed right now.
+static CURRENT_SALE: Mutex<Option<CurrentSale>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    Upi,
+    /// Taken by an attendant, who approved the sale with their PIN.
+    Cash,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleStatus {
+    /// The QR is showing and no payment has been seen yet.
+    Pending,
+    /// The QR closed without a payment.
+    Expired,
+    Paid,
+    /// Paid and printed.
+    Fulfilled,
+    /// A refund has been asked of Razorpay and its outcome is not recorded
+    /// yet. A sale left like this by a crash has to be checked on the Razorpay
+    /// dashboard.
+    Refunding,
+    Refunded,
+    RefundFailed,
+}
+
+struct CurrentSale {
+    id: String,
+    failed_prints: u32,
+}
+
+/// One guest purchase. Amounts are in paise.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sale {
+    /// The Razorpay QR code id for UPI sales.
+    pub id: String,
+    pub method: PaymentMethod,
+    pub amount: u64,
+    pub status: SaleStatus,
+    /// Unix timestamps, in seconds.
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub payment_id: Option<String>,
+    #[serde(default)]
+    pub refund_id: Option<String>,
+    /// Refunds asked of Razorpay for this sale so far.
+    #[serde(default)]
+    pub refund_attempts: u32,
+    /// Why the sale was refunded, or why the refund failed.
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Who approved a cash sale.
+    #[serde(default)]
+    pub attendant: Option<String>,
+    /// Counts up with every sale recorded or updated, so the latest changes
+    /// can be picked out even within the same second.
+    #[serde(default)]
+    pub revision: u64,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct Revenue {
+    pub sales: usize,
+    pub amount: u64,
+}
+
+/// Money kept from paid sales, split the way the takings are reconciled.
+#[derive(Serialize, Default, Debug)]
+pub struct SalesReport {
+    pub upi: Revenue,
+    pub cash: Revenue,
+    /// Cash revenue per attendant, for counting the drawer.
+    pub attendants: BTreeMap<String, Revenue>,
+    pub refunded: Revenue,
+}
+
+impl Sale {
+    pub fn new(id: String, method: PaymentMethod, amount: u64, status: SaleStatus) -> Self {
+        let now = Utc::now().timestamp();
+
+        Sale {
+            id,
+            method,
+            amount,
+            status,
+            created_at: now,
+            updated_at: now,
+            payment_id: None,
+            refund_id: None,
+            refund_attempts: 0,
+            note: None,
+            attendant: None,
+            revision: 0,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_ledger(app: AppHandle) -> Result<Vec<Sale>, Error> {
+    Ok(load(&document_dir(&app).map_err(Error::Storage)?))
+}
+
+/// Sales the guest paid for that never printed, e.g. because the network
+/// dropped before the booth saw the payment, for the attendant to honour or
+/// refund.
+#[tauri::command]
+pub fn unfulfilled_sales(app: AppHandle) -> Result<Vec<Sale>, Error> {
+    let current = current();
+
+    Ok(load(&document_dir(&app).map_err(Error::Storage)?)
+        .into_iter()
+        .filter(|sale| sale.status == SaleStatus::Paid && Some(&sale.id) != current.as_ref())
+        .collect())
+}
+
+/// Records that the attendant delivered a paid sale by hand.
+#[tauri::command]
+pub fn fulfil_sale(app: AppHandle, id: String, token: String) -> Result<Sale, Error> {
+    auth::require(&token)?;
+
+    let directory = document_dir(&app).map_err(Error::Storage)?;
+
+    match find(&directory, &id).map(|sale| sale.status) {
+        Some(SaleStatus::Paid) => {}
+        Some(status) => {
+            return Err(Error::Invalid(format!(
+                "Sale {} cannot be fulfilled while {:?}",
+                id, status
+            )))
+        }
+        None => return Err(Error::Invalid(format!("No sale {} in the ledger", id))),
+    }
+
+    update(&directory, &id, |sale| {
+        sale.status = SaleStatus::Fulfilled;
+        sale.note = Some("Fulfilled by attendant".to_string());
+    })
+    .map_err(Error::Storage)?
+    .ok_or_else(|| Error::Invalid(format!("No sale {} in the ledger", id)))
+}
+
+#[tauri::command]
+pub fn sales_report(app: AppHandle) -> Result<SalesReport, Error> {
+    Ok(report(&load(&document_dir(&app).map_err(Error::Storage)?)))
+}
+
+/// Refunds a sale from the admin panel, e.g. for a print that came out badly.
+/// Cash sales are only marked refunded; the attendant hands the cash back.
+#[tauri::command(async)]
+pub async fn refund_sale(
+    app: AppHandle,
+    id: String,
+    reason: String,
+    token: String,
+) -> Result<Sale, Error> {
+    auth::require(&token)?;
+
+    let started = Instant::now();
+    let directory = document_dir(&app).map_err(Error::Storage)?;
+
+    let result = match find(&directory, &id).map(|sale| sale.method) {
+        Some(PaymentMethod::Cash) => cash::refund(&directory, &id, &reason),
+        _ => razorpay::refund(&directory, &id, &reason)
+            .await
+            .map_err(Error::Payment),
+    };
+    logging::command("refund_sale", started, &result);
+
+    result
+}
+
+pub fn document_dir(app: &AppHandle) -> Result<String, String> {
+    app.path()
+        .document_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .map_err(|e| format!("Failed to find documents directory: {}", e))
+}
+
+pub fn load(directory: &str) -> Vec<Sale> {
+    config::load_versioned(directory, LEDGER_FILE, LEDGER_VERSION).unwrap_or_default()
+}
+
+pub fn find(directory: &str, id: &str) -> Option<Sale> {
+    load(directory).into_iter().find(|sale| sale.id == id)
+}
+
+pub fn record(directory: &str, mut sale: Sale) -> Result<(), String> {
+    let _guard = LEDGER_LOCK.lock().unwrap();
+
+    let mut sales = load(directory);
+    sale.revision = next_revision(&sales);
+    sales.push(sale);
+
+    config::save_versioned(directory, LEDGER_FILE, LEDGER_VERSION, &sales)
+}
+
+/// Applies `change` to the sale `id`, returning it as updated, or `None`
+/// when the ledger has no such sale.
+pub fn update(
+    directory: &str,
+    id: &str,
+    change: impl FnOnce(&mut Sale),
+) -> Result<Option<Sale>, String> {
+    let _guard = LEDGER_LOCK.lock().unwrap();
+
+    let mut sales = load(directory);
+    let revision = next_revision(&sales);
+    let Some(sale) = sales.iter_mut().find(|sale| sale.id == id) else {
+        return Ok(None);
+    };
+
+    change(sale);
+    sale.updated_at = Utc::now().timestamp();
+    sale.revision = revision;
+    let updated = sale.clone();
+
+    config::save_versioned(directory, LEDGER_FILE, LEDGER_VERSION, &sales)?;
+
+    Ok(Some(updated))
+}
+
+/// Like [`update`], but `change` can refuse, in which case the ledger is left
+/// as it was and its error returned.
+pub fn try_update(
+    directory: &str,
+    id: &str,
+    change: impl FnOnce(&mut Sale) -> Result<(), String>,
+) -> Result<Sale, String> {
+    let _guard = LEDGER_LOCK.lock().unwrap();
+
+    let mut sales = load(directory);
+    let revision = next_revision(&sales);
+    let sale = sales
+        .iter_mut()
+        .find(|sale| sale.id == id)
+        .ok_or_else(|| format!("No sale {} in the ledger", id))?;
+
+    change(sale)?;
+    sale.updated_at = Utc::now().timestamp();
+    sale.revision = revision;
+    let updated = sale.clone();
+
+    config::save_versioned(directory, LEDGER_FILE, LEDGER_VERSION, &sales)?;
+
+    Ok(updated)
+}
+
+fn next_revision(sales: &[Sale]) -> u64 {
+    sales.iter().map(|sale| sale.revision).max().unwrap_or(0) + 1
+}
+
+pub fn current() -> Option<String> {
+    CURRENT_SALE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|current| current.id.clone())
+}
+
+pub fn set_current(id: Option<String>) {
+    *CURRENT_SALE.lock().unwrap() = id.map(|id| CurrentSale {
+        id,
+        failed_prints: 0,
+    });
+}
+
+/// Gives up on printing the current sale after a failed print, e.g. when the
+/// guest walks away, and refunds it.
+#[tauri::command]
+pub fn abandon_print(app: AppHandle) -> Result<(), Error> {
+    let mut current = CURRENT_SALE.lock().unwrap();
+    let Some(sale) = current.take() else {
+        return Ok(());
+    };
+
+    // Nothing to give up on until a print has failed
+    if sale.failed_prints == 0 {
+        *current = Some(sale);
+        return Ok(());
+    }
+    drop(current);
+
+    let directory = document_dir(&app).map_err(Error::Storage)?;
+    give_up(
+        directory,
+        sale.id,
+        "Print abandoned after failing".to_string(),
+    );
+
+    Ok(())
+}
+
+/// Settles the current sale once its print has been submitted. It is
+/// fulfilled when the print went through. A failed print keeps it current so
+/// the guest can try again, until `MAX_PRINT_ATTEMPTS` prints have failed and
+/// it is given up on. Returns the prints the guest has left after a failure,
+/// or `None` when no sale is riding on the print.
+pub fn settle_print(app: &AppHandle, result: &Result<(), Error>) -> Option<u32> {
+    let (id, failed_prints) = {
+        let mut current = CURRENT_SALE.lock().unwrap();
+        let sale = current.as_mut()?;
+        if result.is_err() {
+            sale.failed_prints += 1;
+        }
+        let settled = (sale.id.clone(), sale.failed_prints);
+
+        if result.is_ok() || sale.failed_prints >= MAX_PRINT_ATTEMPTS {
+            *current = None;
+        }
+        settled
+    };
+    let attempts_left = result
+        .is_err()
+        .then(|| MAX_PRINT_ATTEMPTS.saturating_sub(failed_prints));
+    let directory = match document_dir(app) {
+        Ok(directory) => directory,
+        Err(e) => {
+            log::error!("Failed to settle sale {}: {}", id, e);
+            return attempts_left;
+        }
+    };
+
+    match result {
+        Ok(()) => {
+            if let Err(e) = update(&directory, &id, |sale| sale.status = SaleStatus::Fulfilled) {
+                log::error!("Failed to record sale {} as fulfilled: {}", id, e);
+            }
+        }
+        Err(error) if failed_prints < MAX_PRINT_ATTEMPTS => {
+            let note = format!("Print attempt {} failed: {}", failed_prints, error);
+
+            if let Err(e) = update(&directory, &id, |sale| sale.note = Some(note)) {
+                log::error!("Failed to record print failure on sale {}: {}", id, e);
+            }
+        }
+        Err(error) => give_up(directory, id, format!("Print failed: {}", error)),
+    }
+
+    attempts_left
+}
+
+/// Refunds a sale that will not be printed, in the background.
+fn give_up(directory: String, id: String, reason: String) {
+    // There is no one to hand cash back automatically, so leave the sale for
+    // the attendant to refund from the sales page
+    if find(&directory, &id).is_some_and(|sale| sale.method == PaymentMethod::Cash) {
+        if let Err(e) = update(&directory, &id, |sale| sale.note = Some(reason)) {
+            log::error!("Failed to record print failure on sale {}: {}", id, e);
+        }
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = razorpay::refund(&directory, &id, &reason).await {
+            log::error!("Failed to refund sale {}: {}", id, e);
+        }
+    });
+}
+
+/// Totals the money kept from `sales`. Pending and expired sales were never
+/// paid and refunded ones were given back, so none count as revenue.
+pub fn report(sales: &[Sale]) -> SalesReport {
+    let mut report = SalesReport::default();
+
+    for sale in sales {
+        let revenue = match (sale.status, sale.method) {
+            (SaleStatus::Pending | SaleStatus::Expired, _) => continue,
+            (SaleStatus::Refunded, _) => &mut report.refunded,
+            (_, PaymentMethod::Upi) => &mut report.upi,
+            (_, PaymentMethod::Cash) => {
+                let attendant = sale.attendant.clone().unwrap_or_default();
+                let by_attendant = report.attendants.entry(attendant).or_default();
+                by_attendant.sales += 1;
+                by_attendant.amount += sale.amount;
+
+                &mut report.cash
+            }
+        };
+
+        revenue.sales += 1;
+        revenue.amount += sale.amount;
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sale(method: PaymentMethod, amount: u64, status: SaleStatus) -> Sale {
+        Sale::new(format!("sale-{}", amount), method, amount, status)
+    }
+
+    fn cash(amount: u64, status: SaleStatus, attendant: &str) -> Sale {
+        Sale {
+            attendant: Some(attendant.to_string()),
+            ..sale(PaymentMethod::Cash, amount, status)
+        }
+    }
+
+    fn totals(revenue: &Revenue) -> (usize, u64) {
+        (revenue.sales, revenue.amount)
+    }
+
+    #[test]
+    fn splits_revenue_by_method_and_attendant() {
+        let sales = [
+            sale(PaymentMethod::Upi, 20000, SaleStatus::Fulfilled),
+            sale(PaymentMethod::Upi, 30000, SaleStatus::Paid),
+            // The refund was attempted and failed, so the money was kept
+            sale(PaymentMethod::Upi, 15000, SaleStatus::RefundFailed),
+            cash(25000, SaleStatus::Fulfilled, "Priya"),
+            cash(10000, SaleStatus::Fulfilled, "Priya"),
+            cash(40000, SaleStatus::Paid, "Arjun"),
+        ];
+
+        let report = report(&sales);
+
+        assert_eq!(totals(&report.upi), (3, 65000));
+        assert_eq!(totals(&report.cash), (3, 75000));
+        assert_eq!(totals(&report.attendants["Priya"]), (2, 35000));
+        assert_eq!(totals(&report.attendants["Arjun"]), (1, 40000));
+        assert_eq!(totals(&report.refunded), (0, 0));
+    }
+
+    #[test]
+    fn leaves_unpaid_and_refunded_sales_out_of_revenue() {
+        let sales = [
+            sale(PaymentMethod::Upi, 20000, SaleStatus::Pending),
+            sale(PaymentMethod::Upi, 30000, SaleStatus::Expired),
+            sale(PaymentMethod::Upi, 15000, SaleStatus::Refunded),
+            cash(25000, SaleStatus::Refunded, "Priya"),
+        ];
+
+        let report = report(&sales);
+
+        assert_eq!(totals(&report.upi), (0, 0));
+        assert_eq!(totals(&report.cash), (0, 0));
+        assert!(report.attendants.is_empty());
+        assert_eq!(totals(&report.refunded), (2, 40000));
+    }
+}
@@ -5,6 +5,24 @@ mod razorpay;
 mod mail;
 mod imaging;
 mod config;
+mod photos;
+mod faces;
+mod color;
+mod session;
+mod preview;
+mod animation;
+mod camera;
+mod exposure;
+mod printer;
+mod readiness;
+mod ledger;
+mod cash;
+mod error;
+mod logging;
+mod diagnostics;
+mod fleet;
+mod admin_api;
+mod auth;
  
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -12,8 +30,42 @@ pub fn run() {
     .invoke_handler(tauri::generate_handler![
       razorpay::create_qr,
       razorpay::check_payment_status,
-      imaging::capture,
+      readiness::booth_readiness,
+      ledger::get_ledger,
+      ledger::refund_sale,
+      ledger::sales_report,
+      ledger::unfulfilled_sales,
+      ledger::fulfil_sale,
+      ledger::abandon_print,
+      cash::record_cash_payment,
+      logging::export_logs,
+      diagnostics::export_diagnostics,
+      fleet::save_fleet_settings,
+      fleet::get_or_init_fleet_settings,
+      fleet::fleet_status,
+      fleet::sync_fleet,
+      admin_api::save_admin_api_settings,
+      admin_api::get_or_init_admin_api_settings,
+      auth::admin_login,
+      auth::admin_logout,
+      auth::change_admin_pin,
+      camera::capture,
+      camera::list_cameras,
+      camera::camera_self_test,
+      exposure::calibrate_exposure,
       imaging::print,
+      imaging::render_preview,
+      imaging::print_rendered,
+      imaging::print_test_page,
+      printer::printer_status,
+      preview::start_preview,
+      preview::stop_preview,
+      animation::capture_animation,
+      session::start_session,
+      session::capture_slot,
+      session::select_take,
+      session::get_session,
+      session::end_session,
       mail::store_email,
       mail::send_email,
       config::save_config,
@@ -22,6 +74,10 @@ pub fn run() {
       config::get_or_init_layouts,
       config::save_pages,
       config::get_or_init_pages,
+      config::save_printers,
+      config::get_or_init_printers,
+      config::save_capture_settings,
+      config::get_or_init_capture_settings,
     ])
     .setup(|app| {
       let window = app.get_webview_window("main").unwrap();
@@ -31,13 +87,17 @@ pub fn run() {
           let _ = window.set_fullscreen(true);
       });
 
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Trace)
-            .build(),
-        )?;
-      }
+      let level = ledger::document_dir(app.handle())
+        .ok()
+        .and_then(|directory| config::load_config(&directory))
+        .map(|config| config.log_level)
+        .unwrap_or_default();
+      app.handle().plugin(logging::plugin(logging::log_dir(app.handle())?, level))?;
+
+      razorpay::spawn_reconciler(app.handle().clone());
+      fleet::spawn_agent(app.handle().clone());
+      admin_api::spawn(app.handle().clone());
+
       Ok(())
     })
     .run(tauri::generate_context!())
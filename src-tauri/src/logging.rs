@@ -0,0 +1,288 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{Local, SecondsFormat, Utc};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{auth, error::Error, ledger, session};
+
+const LOG_FILE: &str = "memorabooth";
+/// Size at which the log is rotated on the next start.
+const MAX_FILE_SIZE: u128 = 5 * 1024 * 1024;
+/// Rotated logs older than this are deleted on start.
+const RETENTION_DAYS: u64 = 30;
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Where the rotating log files live, under the app data directory.
+pub fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("logs"))
+        .map_err(|e| format!("Failed to find app data directory: {}", e))
+}
+
+/// The logger: one timestamped line per record, written to rotating files
+/// at `level`, and to stdout as well in debug builds.
+pub fn plugin<R: Runtime>(dir: PathBuf, level: LogLevel) -> tauri::plugin::TauriPlugin<R> {
+    prune(&dir);
+
+    let mut builder = tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .target(Target::new(TargetKind::Folder {
+            path: dir,
+            file_name: Some(LOG_FILE.to_string()),
+        }))
+        .rotation_strategy(RotationStrategy::KeepAll)
+        .max_file_size(MAX_FILE_SIZE)
+        .level(LevelFilter::from(level))
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} {} {}: {}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                record.level(),
+                record.target(),
+                message
+            ))
+        });
+
+    if cfg!(debug_assertions) {
+        builder = builder
+            .target(Target::new(TargetKind::Stdout))
+            .level(LevelFilter::Trace);
+    }
+
+    builder.build()
+}
+
+/// Records how a command went, with the capture session and sale it was
+/// part of, as `key=value` pairs.
+pub fn command<T, E: fmt::Display>(name: &str, started: Instant, result: &Result<T, E>) {
+    let session = session::current_id().unwrap_or_else(|| "-".to_string());
+    let sale = ledger::current().unwrap_or_else(|| "-".to_string());
+    let line = command_line(
+        name,
+        &session,
+        &sale,
+        started.elapsed().as_millis(),
+        result.as_ref().err(),
+    );
+
+    match result {
+        Ok(_) => log::info!(target: "command", "{}", line),
+        Err(_) => log::error!(target: "command", "{}", line),
+    }
+}
+
+/// The line [`command`] logs. The error is quoted, so it can't be mistaken
+/// for more pairs.
+fn command_line(
+    name: &str,
+    session: &str,
+    sale: &str,
+    duration_ms: u128,
+    error: Option<&impl fmt::Display>,
+) -> String {
+    let line = format!(
+        "command={} session={} sale={} duration_ms={}",
+        name, session, sale, duration_ms
+    );
+
+    match error {
+        None => format!("{} outcome=ok", line),
+        Some(e) => format!("{} outcome=error error={:?}", line, e.to_string()),
+    }
+}
+
+/// Zips the logs written in the last `days` days into `destination`, e.g. a
+/// USB drive, and returns the archive's path.
+#[tauri::command(async)]
+pub async fn export_logs(
+    app: AppHandle,
+    days: u64,
+    destination: String,
+    token: String,
+) -> Result<String, Error> {
+    auth::require(&token)?;
+
+    let dir = log_dir(&app).map_err(Error::Storage)?;
+    let archive = PathBuf::from(destination).join(format!(
+        "memorabooth-logs-{}.zip",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    let path = archive.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let entries = recent_logs(&dir, days)?
+            .into_iter()
+            .map(|file| {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let data = fs::read(&file)
+                    .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+
+                Ok((format!("logs/{}", name), data))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        write_zip(&path, entries)
+    })
+    .await
+    .map_err(|e| Error::Storage(format!("Log export failed: {}", e)))?
+    .map_err(Error::Storage)?;
+
+    Ok(archive.to_string_lossy().to_string())
+}
+
+/// The log files in `dir` written to in the last `days` days.
+pub fn recent_logs(dir: &Path, days: u64) -> Result<Vec<PathBuf>, String> {
+    let cutoff = SystemTime::now() - Duration::from_secs(days.max(1) * DAY_SECS);
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .filter(|path| modified(path).is_some_and(|modified| modified >= cutoff))
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Writes `entries`, as (name in the archive, contents), to a new zip at `path`.
+pub fn write_zip(path: &Path, entries: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, data) in entries {
+        zip.start_file(name.as_str(), options)
+            .and_then(|_| zip.write_all(&data).map_err(Into::into))
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(())
+}
+
+/// Deletes rotated logs past the retention period.
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let cutoff = SystemTime::now() - Duration::from_secs(RETENTION_DAYS * DAY_SECS);
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let is_log = path.extension().is_some_and(|ext| ext == "log");
+
+        if is_log && modified(&path).is_some_and(|modified| modified < cutoff) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("memorabooth-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Creates `name` in `dir`, last written `days` days ago.
+    fn write_aged(dir: &Path, name: &str, days: u64) -> PathBuf {
+        let path = dir.join(name);
+        let file = File::create(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(days * DAY_SECS))
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn prunes_logs_past_the_retention_period() {
+        let dir = scratch("log-prune");
+        let expired = write_aged(&dir, "memorabooth_2024-01-01.log", RETENTION_DAYS + 1);
+        let kept = write_aged(&dir, "memorabooth_2024-02-01.log", RETENTION_DAYS - 1);
+        let current = write_aged(&dir, "memorabooth.log", 0);
+        let other = write_aged(&dir, "notes.txt", RETENTION_DAYS + 1);
+
+        prune(&dir);
+
+        assert!(!expired.exists());
+        assert!(kept.exists());
+        assert!(current.exists());
+        // Only logs are the logger's to delete
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn exports_only_recent_logs() {
+        let dir = scratch("log-recent");
+        write_aged(&dir, "memorabooth_2024-01-01.log", 5);
+        let yesterday = write_aged(&dir, "memorabooth_2024-01-05.log", 1);
+        let current = write_aged(&dir, "memorabooth.log", 0);
+        write_aged(&dir, "notes.txt", 0);
+
+        assert_eq!(recent_logs(&dir, 3).unwrap(), vec![current, yesterday]);
+    }
+
+    #[test]
+    fn logs_commands_as_key_value_pairs() {
+        assert_eq!(
+            command_line("print", "s1", "-", 1200, None::<&String>),
+            "command=print session=s1 sale=- duration_ms=1200 outcome=ok"
+        );
+        assert_eq!(
+            command_line(
+                "capture",
+                "-",
+                "qr_1",
+                5,
+                Some(&Error::Camera("Timed out after 15s".to_string()))
+            ),
+            "command=capture session=- sale=qr_1 duration_ms=5 outcome=error \
+             error=\"Timed out after 15s\""
+        );
+    }
+}
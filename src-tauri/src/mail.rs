@@ -4,6 +4,7 @@ use chrono::Local;
 use image::{imageops::FilterType::Lanczos3, GenericImage, GenericImageView, Rgba, RgbaImage};
 use imageproc::drawing::draw_text_mut;
 use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use reqwest::Client;
 use serde_json::{json, to_string_pretty, Value};
 use std::{
@@ -14,7 +15,12 @@ use std::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
-use crate::imaging::Layout;
+use crate::{
+    config,
+    error::Error as CommandError,
+    imaging::Layout,
+    photos::{self, PhotoMetadata},
+};
 
 static IS_SENDING: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
 
@@ -27,10 +33,17 @@ pub fn store_email(
     user_email: String,
     photo_paths: Vec<String>,
     layout: Layout,
-) -> Result<String, String> {
+    animation: Option<String>,
+) -> Result<String, CommandError> {
     tauri::async_runtime::spawn(async move {
-        if let Err(e) = store_email_req(document_path.clone(), user_email, photo_paths, layout) {
-            eprintln!("Failed to store emails: {e}");
+        if let Err(e) = store_email_req(
+            document_path.clone(),
+            user_email,
+            photo_paths,
+            layout,
+            animation,
+        ) {
+            log::error!("Failed to store emails: {e}");
             return;
         }
 
@@ -45,7 +58,7 @@ pub fn store_email(
         IS_SENDING.store(false, Ordering::SeqCst);
 
         if let Err(e) = res {
-            eprintln!("Failed to send emails: {e}");
+            log::error!("Failed to send emails: {e}");
         }
     });
 
@@ -57,6 +70,7 @@ fn store_email_req(
     user_email: String,
     photo_paths: Vec<String>,
     layout: Layout,
+    animation: Option<String>,
 ) -> Result<(), String> {
     let mut json_path = PathBuf::from(document_path.clone());
     json_path.push("Memorabooth");
@@ -69,6 +83,7 @@ fn store_email_req(
         user_email.clone(),
         photo_paths,
         layout,
+        animation,
     );
     if let Err(e) = new_photo_paths {
         return Err(format!("Failed to process new paths: {}", e));
@@ -108,13 +123,51 @@ fn store_email_req(
     Ok(())
 }
 
+/// The emails waiting to be sent, without the addresses: how many photos
+/// each one carries, which of them are missing from disk and whether a send
+/// is under way.
+pub fn outbox(directory: &str) -> Value {
+    let path = PathBuf::from(directory)
+        .join("Memorabooth")
+        .join("emails.json");
+    let emails: Vec<Value> = fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+
+    let pending: Vec<Value> = emails
+        .iter()
+        .map(|email| {
+            let photos: Vec<&str> = email["photos"]
+                .as_array()
+                .map(|photos| photos.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            let missing: Vec<&str> = photos
+                .iter()
+                .copied()
+                .filter(|photo| !PathBuf::from(photo).exists())
+                .collect();
+
+            json!({ "attachments": photos.len(), "missing": missing })
+        })
+        .collect();
+
+    json!({
+        "pending": pending.len(),
+        "sending": IS_SENDING.load(Ordering::SeqCst),
+        "emails": pending,
+    })
+}
+
 #[tauri::command]
-pub fn send_email(document_path: String) -> Result<String, String> {
+pub fn send_email(document_path: String) -> Result<String, CommandError> {
     if IS_SENDING
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
         .is_err()
     {
-        return Err("Email sending already in progress.".to_string());
+        return Err(CommandError::Mail(
+            "Email sending already in progress.".to_string(),
+        ));
     }
 
     tauri::async_runtime::spawn(async move {
@@ -123,7 +176,7 @@ pub fn send_email(document_path: String) -> Result<String, String> {
         IS_SENDING.store(false, Ordering::SeqCst);
 
         if let Err(e) = res {
-            eprintln!("Failed to send emails: {}", e);
+            log::error!("Failed to send emails: {}", e);
         }
     });
 
@@ -169,11 +222,18 @@ async fn send_email_req(document_path: String) -> Result<String, String> {
                     .and_then(|f| f.to_str())
                     .unwrap_or("unknown.png")
                     .to_string();
+                let mime_type = if filename.ends_with(".jpg") {
+                    "image/jpeg"
+                } else if filename.ends_with(".gif") {
+                    "image/gif"
+                } else {
+                    "image/png"
+                };
 
                 attachments.push(json!({
                     "name": filename,
                     "content": base64_encoded,
-                    "mime_type": "image/png"
+                    "mime_type": mime_type
                 }));
             }
         }
@@ -207,7 +267,7 @@ async fn send_email_req(document_path: String) -> Result<String, String> {
 
                 for path in photo_paths_arr.iter().filter_map(|p| p.as_str()) {
                     if let Err(e) = remove_file(path) {
-                        eprintln!("Failed to delete file {}: {}", path, e);
+                        log::warn!("Failed to delete file {}: {}", path, e);
                     }
                 }
             }
@@ -243,6 +303,7 @@ fn format_files(
     user_email: String,
     photo_paths: Vec<String>,
     layout: Layout,
+    animation: Option<String>,
 ) -> Result<Vec<String>, Box<dyn Error>> {
     let email_prefix = user_email.split('@').next().unwrap_or("unknown");
     let storage_dir = PathBuf::from(&document_path).join("Memorabooth");
@@ -260,66 +321,83 @@ fn format_files(
 
     let date_text = Local::now().format("%d-%m-%Y").to_string(); // Current date
 
-    let mut polaroid_images: Vec<image::ImageBuffer<Rgba<u8>, Vec<u8>>> = vec![];
-
-    for (index, photo_path) in photo_paths.iter().enumerate().take(4) {
-        let new_filename = format!("{}_polaroid_{}.png", email_prefix, index + 1);
-        let new_path = storage_dir.join(&new_filename);
-
-        let img = image::open(photo_path).map_err(|e| format!("Failed to open photo: {}", e))?;
-        let (width, height) = img.dimensions();
-        let aspect_ratio = width as f32 / height as f32;
-
-        let resized_width = polaroid_size - (2 * border_width);
-        let resized_height = (resized_width as f32 / aspect_ratio) as u32;
-
-        let resized = img.resize(resized_width, resized_height, Lanczos3);
-
-        let polaroid_height = resized_height + (2 * border_width) + 120;
-
-        // Create Polaroid-style canvas
-        let mut polaroid =
-            RgbaImage::from_pixel(polaroid_size, polaroid_height, Rgba([255, 255, 255, 255]));
-
-        polaroid
-            .copy_from(&resized, border_width, border_width)
-            .map_err(|e| format!("Failed to place photo: {}", e))?;
-
-        // Add date (Red)
-        draw_text_mut(
-            &mut polaroid,
-            Rgba([78, 52, 46, 255]), // Red
-            (border_width + 20).try_into().unwrap(),
-            (polaroid_height - border_width - 80).try_into().unwrap(),
-            PxScale::from(70.0),
-            &font,
-            &date_text,
-        );
-
-        // Add "Memorabooth" (Blue)
-        draw_text_mut(
-            &mut polaroid,
-            Rgba([78, 52, 46, 255]), // Blue
-            (polaroid_size - 600).try_into().unwrap(),
-            (polaroid_height - border_width - 80).try_into().unwrap(),
-            PxScale::from(70.0),
-            &font,
-            "M E M O R A B O O T H",
-        );
-
-        // Save the polaroid image
-        polaroid
-            .save(&new_path)
-            .map_err(|e| format!("Failed to save polaroid image: {}", e))?;
-        renamed_paths.push(new_path.to_string_lossy().to_string());
-        polaroid_images.push(polaroid);
-    }
+    let (event_name, booth_id) = config::load_config(&document_path)
+        .map(|config| (config.event_name, config.booth_id))
+        .unwrap_or_default();
+
+    let polaroid_width = polaroid_size - (2 * border_width);
+    // A polaroid is made of at most the first four photos
+    let photos = photos::load_all(
+        &photo_paths[..photo_paths.len().min(4)],
+        Some((polaroid_width, 1)),
+    )?;
+
+    let polaroid_paths = photos
+        .par_iter()
+        .enumerate()
+        .map(|(index, img)| -> Result<String, String> {
+            let new_filename = format!("{}_polaroid_{}.jpg", email_prefix, index + 1);
+            let new_path = storage_dir.join(&new_filename);
+
+            let (width, height) = img.dimensions();
+            let aspect_ratio = width as f32 / height as f32;
+
+            let resized_height = (polaroid_width as f32 / aspect_ratio) as u32;
+
+            let resized = img.resize(polaroid_width, resized_height, Lanczos3);
+
+            let polaroid_height = resized_height + (2 * border_width) + 120;
+
+            // Create Polaroid-style canvas
+            let mut polaroid =
+                RgbaImage::from_pixel(polaroid_size, polaroid_height, Rgba([255, 255, 255, 255]));
+
+            polaroid
+                .copy_from(&resized, border_width, border_width)
+                .map_err(|e| format!("Failed to place photo: {}", e))?;
+
+            // Add date (Red)
+            draw_text_mut(
+                &mut polaroid,
+                Rgba([78, 52, 46, 255]), // Red
+                (border_width + 20).try_into().unwrap(),
+                (polaroid_height - border_width - 80).try_into().unwrap(),
+                PxScale::from(70.0),
+                &font,
+                &date_text,
+            );
+
+            // Add "Memorabooth" (Blue)
+            draw_text_mut(
+                &mut polaroid,
+                Rgba([78, 52, 46, 255]), // Blue
+                (polaroid_size - 600).try_into().unwrap(),
+                (polaroid_height - border_width - 80).try_into().unwrap(),
+                PxScale::from(70.0),
+                &font,
+                "M E M O R A B O O T H",
+            );
+
+            // Save the polaroid image
+            let metadata = PhotoMetadata {
+                captured_at: photos::captured_at(&photo_paths[index]),
+                event_name: event_name.clone(),
+                booth_id: booth_id.clone(),
+            };
+            photos::save_jpeg(&polaroid, &new_path, &metadata)
+                .map_err(|e| format!("Failed to save polaroid image: {}", e))?;
+
+            Ok(new_path.to_string_lossy().to_string())
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    renamed_paths.extend(polaroid_paths);
 
     if layout != Layout::A {
         // Create the final collage
         let gap_px = 20;
         let padded_collage_size = (collage_size.0 + (2 * gap_px), collage_size.1 + (2 * gap_px));
-        let collage_path = storage_dir.join(format!("{}_collage.png", email_prefix));
+        let collage_path = storage_dir.join(format!("{}_collage.jpg", email_prefix));
         let mut collage = RgbaImage::from_pixel(
             padded_collage_size.0,
             padded_collage_size.1,
@@ -329,20 +407,23 @@ fn format_files(
         let cell_width = (collage_size.0 - gap_px) / 2;
         let cell_height = (collage_size.1 - 100 - (gap_px * 3)) / 4;
 
-        for (i, photo) in photo_paths.iter().enumerate() {
-            let y_offset = gap_px + (i as u32 * (cell_height + gap_px));
+        let photos = photos::load_all(&photo_paths, Some((cell_width, cell_height)))?;
+        let cells: Vec<RgbaImage> = photos
+            .par_iter()
+            .map(|img| image::imageops::resize(&**img, cell_width, cell_height, Lanczos3))
+            .collect();
 
-            let img = image::open(photo).map_err(|e| format!("Failed to load image: {}", e))?;
-            let resized = image::imageops::resize(&img, cell_width, cell_height, Lanczos3);
+        for (i, resized) in cells.iter().enumerate() {
+            let y_offset = gap_px + (i as u32 * (cell_height + gap_px));
 
             let left_x_offset = gap_px;
             let right_x_offset = cell_width + (2 * gap_px);
 
             collage
-                .copy_from(&resized, left_x_offset, y_offset)
+                .copy_from(resized, left_x_offset, y_offset)
                 .map_err(|e| format!("Failed to place photo in left column: {}", e))?;
             collage
-                .copy_from(&resized, right_x_offset, y_offset)
+                .copy_from(resized, right_x_offset, y_offset)
                 .map_err(|e| format!("Failed to place photo in right column: {}", e))?;
         }
 
@@ -358,11 +439,32 @@ fn format_files(
         );
 
         // Save the collage
-        collage
-            .save(&collage_path)
+        let metadata = PhotoMetadata {
+            captured_at: photo_paths
+                .first()
+                .map(|path| photos::captured_at(path))
+                .unwrap_or_else(Local::now),
+            event_name,
+            booth_id,
+        };
+        photos::save_jpeg(&collage, &collage_path, &metadata)
             .map_err(|e| format!("Failed to save collage: {}", e))?;
         renamed_paths.push(collage_path.to_string_lossy().to_string());
     }
 
+    if let Some(animation) = animation {
+        let animation_path = storage_dir.join(format!("{}_animation.gif", email_prefix));
+
+        // Pictures and documents may sit on different mounts, where rename fails
+        if fs::rename(&animation, &animation_path).is_err() {
+            fs::copy(&animation, &animation_path)
+                .map_err(|e| format!("Failed to store animation: {}", e))?;
+            if let Err(e) = remove_file(&animation) {
+                log::warn!("Failed to delete animation {}: {}", animation, e);
+            }
+        }
+        renamed_paths.push(animation_path.to_string_lossy().to_string());
+    }
+
     Ok(renamed_paths)
 }
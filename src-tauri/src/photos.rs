@@ -0,0 +1,461 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, Cursor},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use exif::{Field, In, Tag, Value};
+use image::{
+    buffer::ConvertBuffer, codecs::jpeg::JpegEncoder, metadata::Orientation, DynamicImage,
+    GrayImage, ImageDecoder, ImageReader, ImageResult, RgbImage, RgbaImage,
+};
+use jpeg_decoder::PixelFormat;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+
+// One guest session never uses more than four shots, so this keeps the
+// current session decoded without holding on to older full-size frames.
+const CACHE_CAPACITY: usize = 4;
+
+struct CachedPhoto {
+    modified: SystemTime,
+    used_at: Instant,
+    scaled: bool,
+    image: Arc<DynamicImage>,
+}
+
+impl CachedPhoto {
+    fn covers(&self, min_size: Option<(u32, u32)>) -> bool {
+        !self.scaled
+            || min_size.is_some_and(|(min_w, min_h)| {
+                self.image.width() >= min_w && self.image.height() >= min_h
+            })
+    }
+}
+
+static PHOTO_CACHE: Lazy<Mutex<HashMap<String, CachedPhoto>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Decodes the photo at `path`, reusing the cached decode as long as the
+/// file has not been rewritten since.
+///
+/// With a `min_size`, JPEGs may be decoded at a reduced scale as long as
+/// both dimensions still cover it; `None` always yields the full image.
+pub fn load(path: &str, min_size: Option<(u32, u32)>) -> Result<Arc<DynamicImage>, String> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+
+    {
+        let mut cache = PHOTO_CACHE.lock().unwrap();
+
+        if let Some(cached) = cache.get_mut(path) {
+            if cached.modified == modified && cached.covers(min_size) {
+                cached.used_at = Instant::now();
+                return Ok(cached.image.clone());
+            }
+        }
+    }
+
+    let (image, scaled) = match min_size.and_then(|(w, h)| decode_jpeg_scaled(path, w, h)) {
+        Some(image) => (image, true),
+        None => (
+            decode_full(path).map_err(|e| format!("Failed to open image {}: {}", path, e))?,
+            false,
+        ),
+    };
+    let image = Arc::new(image);
+
+    let mut cache = PHOTO_CACHE.lock().unwrap();
+
+    if !cache.contains_key(path) && cache.len() >= CACHE_CAPACITY {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, cached)| cached.used_at)
+            .map(|(key, _)| key.clone());
+
+        if let Some(key) = oldest {
+            cache.remove(&key);
+        }
+    }
+
+    cache.insert(
+        path.to_string(),
+        CachedPhoto {
+            modified,
+            used_at: Instant::now(),
+            scaled,
+            image: image.clone(),
+        },
+    );
+
+    Ok(image)
+}
+
+/// Decodes the photo at `path` upright, like [`load`], but without caching
+/// it, for one-off frames that would only push the session's photos out.
+pub fn decode(path: &str, min_size: Option<(u32, u32)>) -> Result<DynamicImage, String> {
+    match min_size.and_then(|(w, h)| decode_jpeg_scaled(path, w, h)) {
+        Some(image) => Ok(image),
+        None => decode_full(path).map_err(|e| format!("Failed to open image {}: {}", path, e)),
+    }
+}
+
+/// Decodes all `paths` in parallel, preserving their order.
+pub fn load_all(
+    paths: &[String],
+    min_size: Option<(u32, u32)>,
+) -> Result<Vec<Arc<DynamicImage>>, String> {
+    paths.par_iter().map(|path| load(path, min_size)).collect()
+}
+
+/// Drops the cached decode for `path`, e.g. before a new capture replaces it.
+pub fn forget(path: &str) {
+    PHOTO_CACHE.lock().unwrap().remove(path);
+}
+
+/// Decodes the whole image, upright according to its EXIF orientation.
+fn decode_full(path: &str) -> ImageResult<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    Ok(image)
+}
+
+/// Decodes a JPEG using the smallest IDCT scale (1/8, 1/4 or 1/2) that still
+/// covers `min_w`×`min_h` once upright. Returns `None` when the file is not a
+/// JPEG we can scale, or when a full decode is needed anyway.
+fn decode_jpeg_scaled(path: &str, min_w: u32, min_h: u32) -> Option<DynamicImage> {
+    fn scaled(len: u32, eighths: u32) -> u32 {
+        (len * eighths - 1) / 8 + 1
+    }
+
+    let file = File::open(path).ok()?;
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(file));
+
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let (full_w, full_h) = (info.width as u32, info.height as u32);
+
+    if !matches!(info.pixel_format, PixelFormat::RGB24 | PixelFormat::L8) {
+        return None;
+    }
+
+    let orientation = decoder
+        .exif_data()
+        .map(exif_orientation)
+        .unwrap_or(Orientation::NoTransforms);
+
+    // The minimum applies to the upright image, so swap it for sideways frames
+    let (min_w, min_h) = match orientation {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => (min_h, min_w),
+        _ => (min_w, min_h),
+    };
+
+    let eighths = [1, 2, 4]
+        .into_iter()
+        .find(|&s| scaled(full_w, s) >= min_w && scaled(full_h, s) >= min_h)?;
+
+    let (width, height) = decoder
+        .scale(
+            scaled(full_w, eighths) as u16,
+            scaled(full_h, eighths) as u16,
+        )
+        .ok()?;
+    let pixels = decoder.decode().ok()?;
+
+    let mut image = match info.pixel_format {
+        PixelFormat::RGB24 => {
+            RgbImage::from_raw(width as u32, height as u32, pixels).map(DynamicImage::ImageRgb8)
+        }
+        PixelFormat::L8 => {
+            GrayImage::from_raw(width as u32, height as u32, pixels).map(DynamicImage::ImageLuma8)
+        }
+        _ => None,
+    }?;
+    image.apply_orientation(orientation);
+
+    Some(image)
+}
+
+fn exif_orientation(exif: &[u8]) -> Orientation {
+    exif::Reader::new()
+        .read_raw(exif.to_vec())
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .and_then(|value| Orientation::from_exif(value as u8))
+        .unwrap_or(Orientation::NoTransforms)
+}
+
+/// Booth-side details stamped into photos that leave the booth.
+pub struct PhotoMetadata {
+    pub captured_at: DateTime<Local>,
+    pub event_name: String,
+    pub booth_id: String,
+}
+
+/// When the photo at `path` was taken, going by when the capture was written.
+pub fn captured_at(path: &str) -> DateTime<Local> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(DateTime::<Local>::from)
+        .unwrap_or_else(|_| Local::now())
+}
+
+/// Encodes `image` as a JPEG whose EXIF holds only our own tags, so camera
+/// serials and GPS from the source frames never end up in guests' inboxes.
+pub fn save_jpeg(image: &RgbaImage, path: &Path, metadata: &PhotoMetadata) -> Result<(), String> {
+    let rgb: RgbImage = image.convert();
+
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, 90)
+        .encode_image(&rgb)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+    let date = metadata.captured_at.format("%Y:%m:%d %H:%M:%S").to_string();
+    let software = format!("Memorabooth {}", env!("CARGO_PKG_VERSION"));
+
+    let fields: Vec<Field> = [
+        (Tag::Make, "Memorabooth"),
+        (Tag::Model, metadata.booth_id.as_str()),
+        (Tag::ImageDescription, metadata.event_name.as_str()),
+        (Tag::Software, software.as_str()),
+        (Tag::DateTime, date.as_str()),
+        (Tag::DateTimeOriginal, date.as_str()),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(tag, value)| Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Ascii(vec![value.as_bytes().to_vec()]),
+    })
+    .collect();
+
+    insert_exif(&mut jpeg, &fields)?;
+
+    fs::write(path, jpeg).map_err(|e| format!("Failed to save image: {}", e))
+}
+
+/// Writes `fields` into `jpeg` as its EXIF segment.
+fn insert_exif(jpeg: &mut Vec<u8>, fields: &[Field]) -> Result<(), String> {
+    let mut writer = exif::experimental::Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+
+    let mut tiff = Cursor::new(Vec::new());
+    writer
+        .write(&mut tiff, false)
+        .map_err(|e| format!("Failed to write EXIF: {}", e))?;
+    let tiff = tiff.into_inner();
+
+    let mut app1 = vec![0xFF, 0xE1];
+    app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(&tiff);
+
+    // EXIF goes right after SOI, or after the JFIF header when there is one
+    let mut insert_at = 2;
+    if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+        insert_at += 2 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+    }
+    jpeg.splice(insert_at..insert_at, app1);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use image::Rgb;
+
+    use super::*;
+
+    /// Serialises the tests that go through the shared cache.
+    static CACHE: Mutex<()> = Mutex::new(());
+
+    fn directory(name: &str) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("memorabooth-photos-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    /// A JPEG whose left half is red and right half blue.
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        });
+
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, 90)
+            .encode_image(&image)
+            .unwrap();
+
+        jpeg
+    }
+
+    fn write_jpeg(path: &Path, width: u32, height: u32) -> String {
+        fs::write(path, jpeg(width, height)).unwrap();
+
+        path.to_string_lossy().to_string()
+    }
+
+    fn cached(path: &str) -> bool {
+        PHOTO_CACHE.lock().unwrap().contains_key(path)
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_photo() {
+        let _lock = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = directory("lru");
+        PHOTO_CACHE.lock().unwrap().clear();
+
+        let paths: Vec<_> = (0..=CACHE_CAPACITY)
+            .map(|i| write_jpeg(&directory.join(format!("{}.jpg", i)), 16, 16))
+            .collect();
+
+        for path in &paths[..CACHE_CAPACITY] {
+            load(path, None).unwrap();
+        }
+        // Using the first photo again makes the second the oldest
+        load(&paths[0], None).unwrap();
+        load(&paths[CACHE_CAPACITY], None).unwrap();
+
+        assert!(cached(&paths[0]));
+        assert!(!cached(&paths[1]));
+        assert!(paths[2..].iter().all(|path| cached(path)));
+        assert_eq!(PHOTO_CACHE.lock().unwrap().len(), CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn decodes_again_once_the_file_changes() {
+        let _lock = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = directory("mtime");
+        let path = write_jpeg(&directory.join("photo.jpg"), 16, 16);
+
+        let first = load(&path, None).unwrap();
+        assert!(Arc::ptr_eq(&first, &load(&path, None).unwrap()));
+
+        write_jpeg(Path::new(&path), 32, 16);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+
+        let second = load(&path, None).unwrap();
+        assert_eq!(second.width(), 32);
+
+        forget(&path);
+        assert!(!cached(&path));
+    }
+
+    #[test]
+    fn picks_the_smallest_scale_that_covers_the_size() {
+        let directory = directory("scale");
+        let path = write_jpeg(&directory.join("photo.jpg"), 800, 600);
+        let size = |min_w, min_h| {
+            decode_jpeg_scaled(&path, min_w, min_h).map(|image| (image.width(), image.height()))
+        };
+
+        assert_eq!(size(100, 75), Some((100, 75)));
+        assert_eq!(size(101, 75), Some((200, 150)));
+        assert_eq!(size(300, 300), Some((400, 300)));
+        // Anything bigger than half needs the full decode
+        assert_eq!(size(401, 1), None);
+        assert_eq!(size(1, 301), None);
+    }
+
+    #[test]
+    fn turns_photos_upright() {
+        let directory = directory("orientation");
+        let path = directory.join("rotated.jpg");
+
+        // Stored on its side, tagged to be turned a quarter clockwise
+        let mut jpeg = jpeg(80, 40);
+        insert_exif(
+            &mut jpeg,
+            &[Field {
+                tag: Tag::Orientation,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![6]),
+            }],
+        )
+        .unwrap();
+        fs::write(&path, jpeg).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let full = decode_full(&path).unwrap().to_rgb8();
+        // The sideways minimum still picks 1/8, from the swapped sides
+        let scaled = decode_jpeg_scaled(&path, 5, 10).unwrap().to_rgb8();
+
+        for image in [full, scaled] {
+            let (width, height) = image.dimensions();
+            assert_eq!(width * 2, height);
+
+            // The left half, red, is now on top
+            let top = image.get_pixel(width / 2, height / 4);
+            let bottom = image.get_pixel(width / 2, height * 3 / 4);
+            assert!(top[0] > 200 && top[2] < 60, "{:?}", top);
+            assert!(bottom[2] > 200 && bottom[0] < 60, "{:?}", bottom);
+        }
+    }
+
+    #[test]
+    fn stamps_only_booth_exif() {
+        let directory = directory("exif");
+        let path = directory.join("stamped.jpg");
+        let metadata = PhotoMetadata {
+            captured_at: Local::now(),
+            event_name: String::new(),
+            booth_id: "booth-7".to_string(),
+        };
+
+        save_jpeg(&RgbaImage::new(32, 24), &path, &metadata).unwrap();
+
+        let exif = exif::Reader::new()
+            .read_from_container(&mut BufReader::new(File::open(&path).unwrap()))
+            .unwrap();
+        let text = |tag| {
+            exif.get_field(tag, In::PRIMARY)
+                .map(|field| field.display_value().to_string())
+        };
+
+        assert_eq!(text(Tag::Make).as_deref(), Some("\"Memorabooth\""));
+        assert_eq!(text(Tag::Model).as_deref(), Some("\"booth-7\""));
+        assert_eq!(
+            text(Tag::DateTimeOriginal),
+            Some(metadata.captured_at.format("%Y-%m-%d %H:%M:%S").to_string())
+        );
+        // Empty details are left out rather than written blank
+        assert_eq!(text(Tag::ImageDescription), None);
+        assert_eq!(exif.fields().count(), 5);
+
+        // And the file is still a JPEG the booth can open
+        assert_eq!(decode_full(&path.to_string_lossy()).unwrap().width(), 32);
+    }
+}
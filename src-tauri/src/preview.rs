@@ -0,0 +1,227 @@
+#[cfg(target_os = "linux")]
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+};
+use std::{
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::Error;
+
+#[cfg(target_os = "linux")]
+use crate::camera::CAPTURE_ROI;
+
+const PREVIEW_EVENT: &str = "preview-frame";
+
+struct PreviewStream {
+    running: Arc<AtomicBool>,
+    child: Option<Child>,
+}
+
+static PREVIEW: Lazy<Mutex<Option<PreviewStream>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Clone)]
+pub struct PreviewFrame {
+    pub image: String,
+}
+
+/// Streams camera frames to the webview as `preview-frame` events until
+/// `stop_preview` is called or a capture takes over the camera.
+#[tauri::command]
+pub fn start_preview(app: AppHandle, mirror: bool) -> Result<(), Error> {
+    stop();
+
+    let running = Arc::new(AtomicBool::new(true));
+
+    #[cfg(target_os = "linux")]
+    let child = {
+        let mut cmd = Command::new("libcamera-vid");
+        cmd.arg("-t")
+            .arg("0")
+            .arg("--nopreview")
+            .arg("--codec")
+            .arg("mjpeg")
+            .arg("--width")
+            .arg("960")
+            .arg("--height")
+            .arg("540")
+            .arg("--framerate")
+            .arg("15")
+            .arg("--roi")
+            .arg(CAPTURE_ROI);
+
+        if mirror {
+            cmd.arg("--hflip");
+        }
+
+        let mut child = cmd
+            .arg("-o")
+            .arg("-")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::Camera(format!("Failed to start camera preview: {}", e)))?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Camera("Camera preview has no output".to_string()))?;
+        let flag = running.clone();
+
+        thread::spawn(move || stream_mjpeg(app, stdout, flag));
+
+        Some(child)
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    let child = {
+        let _ = mirror;
+        let flag = running.clone();
+
+        thread::spawn(move || stream_sample(app, flag));
+
+        None
+    };
+
+    *PREVIEW.lock().unwrap() = Some(PreviewStream { running, child });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_preview() -> Result<(), Error> {
+    stop();
+
+    Ok(())
+}
+
+/// Stops the preview stream, if any, and releases the camera.
+pub fn stop() {
+    if let Some(mut stream) = PREVIEW.lock().unwrap().take() {
+        stream.running.store(false, Ordering::SeqCst);
+
+        if let Some(child) = stream.child.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn emit_frame(app: &AppHandle, jpeg: &[u8]) {
+    let frame = PreviewFrame {
+        image: format!("data:image/jpeg;base64,{}", BASE64_STANDARD.encode(jpeg)),
+    };
+
+    if let Err(e) = app.emit(PREVIEW_EVENT, frame) {
+        log::error!("Failed to emit preview frame: {}", e);
+    }
+}
+
+/// Splits the MJPEG byte stream into JPEGs on their SOI/EOI markers.
+#[cfg(target_os = "linux")]
+fn stream_mjpeg(app: AppHandle, mut stdout: impl Read, running: Arc<AtomicBool>) {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 64 * 1024];
+
+    while running.load(Ordering::SeqCst) {
+        let read = match stdout.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        buffer.extend_from_slice(&chunk[..read]);
+
+        while let Some(jpeg) = next_frame(&mut buffer) {
+            emit_frame(&app, &jpeg);
+        }
+    }
+}
+
+/// Takes the first complete JPEG out of `buffer`, dropping anything before
+/// its SOI. A partial frame stays in `buffer` until the rest is read.
+#[cfg(any(target_os = "linux", test))]
+fn next_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let Some(start) = buffer.windows(2).position(|w| w == [0xFF, 0xD8]) else {
+        // Keep a trailing 0xFF, it may be the first half of the next SOI
+        let keep = usize::from(buffer.last() == Some(&0xFF));
+        buffer.drain(..buffer.len() - keep);
+        return None;
+    };
+
+    buffer.drain(..start);
+
+    let end = buffer.windows(2).position(|w| w == [0xFF, 0xD9])? + 2;
+
+    Some(buffer.drain(..end).collect())
+}
+
+/// Without libcamera, the sample image stands in for the camera.
+#[cfg(not(target_os = "linux"))]
+fn stream_sample(app: AppHandle, running: Arc<AtomicBool>) {
+    while running.load(Ordering::SeqCst) {
+        match std::fs::read("sample.jpg") {
+            Ok(jpeg) => emit_frame(&app, &jpeg),
+            Err(e) => {
+                log::error!("Failed to read sample image: {}", e);
+                break;
+            }
+        }
+
+        thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: [u8; 6] = [0xFF, 0xD8, 1, 2, 0xFF, 0xD9];
+
+    #[test]
+    fn splits_back_to_back_frames() {
+        let mut buffer = [FRAME, FRAME].concat();
+
+        assert_eq!(next_frame(&mut buffer), Some(FRAME.to_vec()));
+        assert_eq!(next_frame(&mut buffer), Some(FRAME.to_vec()));
+        assert_eq!(next_frame(&mut buffer), None);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn waits_for_a_frame_split_across_reads() {
+        let mut buffer = FRAME[..3].to_vec();
+        assert_eq!(next_frame(&mut buffer), None);
+        assert_eq!(buffer, FRAME[..3]);
+
+        // The read ends between the two bytes of the EOI
+        buffer.extend_from_slice(&FRAME[3..5]);
+        assert_eq!(next_frame(&mut buffer), None);
+
+        buffer.extend_from_slice(&FRAME[5..]);
+        assert_eq!(next_frame(&mut buffer), Some(FRAME.to_vec()));
+    }
+
+    #[test]
+    fn skips_garbage_before_the_soi() {
+        let mut buffer = [&[7, 0xD9, 0xFF][..], &FRAME].concat();
+        assert_eq!(next_frame(&mut buffer), Some(FRAME.to_vec()));
+
+        // Garbage on its own is dropped, bar a byte that may start an SOI
+        let mut buffer = vec![1, 2, 3, 0xFF];
+        assert_eq!(next_frame(&mut buffer), None);
+        assert_eq!(buffer, [0xFF]);
+
+        buffer.extend_from_slice(&FRAME[1..]);
+        assert_eq!(next_frame(&mut buffer), Some(FRAME.to_vec()));
+    }
+}
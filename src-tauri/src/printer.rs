@@ -0,0 +1,253 @@
+use std::{collections::HashMap, process::Command};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    config::{self, PrinterProfile},
+    error::Error,
+};
+
+/// A consumable the printer reports on, such as an ink cartridge or ribbon.
+#[derive(Serialize, Clone, Debug)]
+pub struct Marker {
+    pub name: String,
+    pub kind: String,
+    /// Percent remaining, when the printer knows it.
+    pub level: Option<u8>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PrinterStatus {
+    pub profile: String,
+    /// The CUPS queue the profile prints to, resolved from the system default
+    /// when the profile leaves it unset.
+    pub queue: Option<String>,
+    pub online: bool,
+    pub state: String,
+    pub accepting_jobs: bool,
+    pub queued_jobs: usize,
+    pub state_reasons: Vec<String>,
+    pub markers: Vec<Marker>,
+}
+
+#[tauri::command(async)]
+pub async fn printer_status(app: AppHandle) -> Result<Vec<PrinterStatus>, Error> {
+    let directory = app
+        .path()
+        .document_dir()
+        .map_err(|e| Error::Storage(format!("Failed to find documents directory: {}", e)))?;
+
+    Ok(config::load_printers(&directory.to_string_lossy())
+        .profiles
+        .iter()
+        .map(status)
+        .collect())
+}
+
+/// Asks CUPS how the queue behind `profile` is doing. Problems reaching the
+/// queue are reported as an offline status rather than an error.
+pub fn status(profile: &PrinterProfile) -> PrinterStatus {
+    let mut status = PrinterStatus {
+        profile: profile.name.clone(),
+        queue: profile.queue.clone().or_else(default_queue),
+        online: false,
+        state: "unknown".to_string(),
+        accepting_jobs: false,
+        queued_jobs: 0,
+        state_reasons: vec![],
+        markers: vec![],
+    };
+
+    let Some(queue) = status.queue.clone() else {
+        status.state_reasons.push("no-default-queue".to_string());
+        return status;
+    };
+
+    let attributes = match Command::new("lpoptions").arg("-p").arg(&queue).output() {
+        Ok(output) if output.status.success() => {
+            parse_attributes(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(output) => {
+            status
+                .state_reasons
+                .push(String::from_utf8_lossy(&output.stderr).trim().to_string());
+            return status;
+        }
+        Err(e) => {
+            status
+                .state_reasons
+                .push(format!("Failed to execute lpoptions: {}", e));
+            return status;
+        }
+    };
+
+    status.state = match attributes.get("printer-state").map(String::as_str) {
+        Some("3") => "idle",
+        Some("4") => "processing",
+        Some("5") => "stopped",
+        _ => "unknown",
+    }
+    .to_string();
+    status.accepting_jobs = attributes
+        .get("printer-is-accepting-jobs")
+        .is_some_and(|value| value == "true");
+    status.state_reasons = attributes
+        .get("printer-state-reasons")
+        .map(|reasons| split_list(reasons))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|reason| reason != "none")
+        .collect();
+    status.markers = markers(&attributes);
+    status.queued_jobs = queued_jobs(&queue);
+
+    status.online = status.state != "stopped"
+        && status.accepting_jobs
+        && !status
+            .state_reasons
+            .iter()
+            .any(|reason| reason.starts_with("offline") || reason.ends_with("-error"));
+
+    status
+}
+
+fn default_queue() -> Option<String> {
+    let output = Command::new("lpstat").arg("-d").output().ok()?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_once(':')
+        .map(|(_, queue)| queue.trim().to_string())
+        .filter(|queue| !queue.is_empty())
+}
+
+fn queued_jobs(queue: &str) -> usize {
+    Command::new("lpstat")
+        .arg("-o")
+        .arg(queue)
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+fn markers(attributes: &HashMap<String, String>) -> Vec<Marker> {
+    let list = |key: &str| {
+        attributes
+            .get(key)
+            .map(|value| split_list(value))
+            .unwrap_or_default()
+    };
+
+    let names = list("marker-names");
+    let kinds = list("marker-types");
+    let levels = list("marker-levels");
+
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| Marker {
+            name,
+            kind: kinds.get(i).cloned().unwrap_or_default(),
+            // Negative levels mean unknown or unavailable
+            level: levels
+                .get(i)
+                .and_then(|level| level.parse::<i32>().ok())
+                .and_then(|level| u8::try_from(level).ok()),
+        })
+        .collect()
+}
+
+/// Splits the `key=value` pairs `lpoptions -p` prints, honouring the quotes
+/// and backslash escapes it uses for values with spaces.
+fn parse_attributes(output: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut token = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = output.trim().chars();
+
+    let mut flush = |token: &mut String| {
+        if let Some((key, value)) = token.split_once('=') {
+            attributes.insert(key.to_string(), value.to_string());
+        }
+        token.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => token.extend(chars.next()),
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => flush(&mut token),
+            (c, _) => token.push(c),
+        }
+    }
+    flush(&mut token);
+
+    attributes
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `lpoptions -p` for a dye-sub printer through CUPS and Gutenprint.
+    const LPOPTIONS: &str = r"copies=1 device-uri=usb://Dai%20Nippon%20Printing/DS620?serial=DS6V8B012345 finishings=3 job-cancel-after=10800 job-hold-until=no-hold job-sheets=none,none marker-change-time=1729330000 marker-colors=#00FFFF#FF00FF#FFFF00,none marker-levels=63,-1 marker-names='Ribbon (6x4),Paper (6x4)' marker-types=ribbonWax,media number-up=1 printer-info='DNP DS620' printer-is-accepting-jobs=true printer-is-shared=false printer-location='Hall\'s east wing' printer-make-and-model='DNP DS620 - CUPS+Gutenprint v5.3.4' printer-state=5 printer-state-change-time=1729330001 printer-state-reasons=media-empty-error,offline-report printer-type=36876
+";
+
+    #[test]
+    fn parses_lpoptions_output() {
+        let attributes = parse_attributes(LPOPTIONS);
+
+        assert_eq!(attributes["printer-state"], "5");
+        assert_eq!(attributes["printer-is-accepting-jobs"], "true");
+        assert_eq!(attributes["printer-info"], "DNP DS620");
+        assert_eq!(attributes["printer-location"], "Hall's east wing");
+        assert_eq!(
+            attributes["printer-make-and-model"],
+            "DNP DS620 - CUPS+Gutenprint v5.3.4"
+        );
+        assert_eq!(
+            attributes["device-uri"],
+            "usb://Dai%20Nippon%20Printing/DS620?serial=DS6V8B012345"
+        );
+        assert_eq!(
+            split_list(&attributes["printer-state-reasons"]),
+            ["media-empty-error", "offline-report"]
+        );
+        assert_eq!(attributes["printer-type"], "36876");
+    }
+
+    #[test]
+    fn reads_markers_from_lpoptions() {
+        let markers = markers(&parse_attributes(LPOPTIONS));
+
+        assert_eq!(markers.len(), 2);
+        assert_eq!(markers[0].name, "Ribbon (6x4)");
+        assert_eq!(markers[0].kind, "ribbonWax");
+        assert_eq!(markers[0].level, Some(63));
+        // The printer does not report paper left
+        assert_eq!(markers[1].name, "Paper (6x4)");
+        assert_eq!(markers[1].kind, "media");
+        assert_eq!(markers[1].level, None);
+    }
+
+    #[test]
+    fn reads_no_markers_without_supplies() {
+        let attributes = parse_attributes("printer-state=3 printer-state-reasons=none");
+
+        assert!(markers(&attributes).is_empty());
+    }
+}
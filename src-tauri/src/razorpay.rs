@@ -1,11 +1,26 @@
+use std::{fmt, thread, time::{self, Instant}};
+
 use chrono::{Duration, Utc};
 
 use once_cell::sync::Lazy;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{
+  error::Error,
+  ledger::{self, PaymentMethod, Sale, SaleStatus},
+  logging,
+  readiness
+};
 
 static RZP_CLIENT: Lazy<Client> = Lazy::new(|| Client::new());
 
+const DEFAULT_API_BASE: &str = "https://api.razorpay.com/v1";
+const RECONCILE_INTERVAL: time::Duration = time::Duration::from_secs(60);
+/// Part of Razorpay's error for refunding a payment it has refunded in full.
+const ALREADY_REFUNDED: &str = "fully refunded already";
+
 #[derive(Serialize)]
 pub struct RazorpayQrRequest {
   #[serde(rename = "type")]
@@ -25,15 +40,85 @@ pub struct RazorpayQrResponse {
 
 #[derive(Deserialize)]
 pub struct RazorpayPollingResponse {
-  pub payments_amount_received: Option<u64>
+  pub payments_amount_received: Option<u64>,
+  /// `active` while the QR accepts payments, `closed` after.
+  #[serde(default)]
+  pub status: Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct RazorpayPayment {
+  pub id: String,
+  pub amount: u64,
+  pub status: String
+}
+
+#[derive(Deserialize)]
+pub struct RazorpayPaymentList {
+  pub items: Vec<RazorpayPayment>
+}
+
+#[derive(Serialize)]
+pub struct RazorpayRefundRequest {
+  pub amount: u64,
+  pub speed: String,
+  pub notes: serde_json::Value
+}
+
+#[derive(Deserialize)]
+pub struct RazorpayRefundResponse {
+  pub id: String
+}
+
+/// Why a Razorpay request failed.
+enum RequestError {
+  /// Razorpay could not be reached.
+  Transport(String),
+  /// Razorpay answered, but with an error or a response we can't read.
+  Api(String)
+}
+
+impl fmt::Display for RequestError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RequestError::Transport(e) | RequestError::Api(e) => write!(f, "{}", e)
+    }
+  }
 }
 
 #[tauri::command(async)]
-pub async fn create_qr(amount: u64, close_by_secs: i64) -> Result<RazorpayQrResponse, String> {
+pub async fn create_qr(
+  app: AppHandle,
+  amount: u64,
+  close_by_secs: i64,
+  copies: Option<u64>
+) -> Result<RazorpayQrResponse, Error> {
+  let started = Instant::now();
+  let result = request_qr(&app, amount, close_by_secs, copies.unwrap_or(1)).await;
+  logging::command("create_qr", started, &result);
+
+  result
+}
+
+async fn request_qr(app: &AppHandle, amount: u64, close_by_secs: i64, copies: u64) -> Result<RazorpayQrResponse, Error> {
+  let directory = ledger::document_dir(app).map_err(Error::Storage)?;
+
+  let check_directory = directory.clone();
+  let readiness = tauri::async_runtime::spawn_blocking(move || {
+    readiness::check(&check_directory, copies)
+  })
+    .await
+    .map_err(|e| Error::Internal(format!("Readiness check failed: {}", e)))?;
+
+  // The booth could not deliver the session, so no payment is requested
+  if !readiness.ready {
+    return Err(Error::BoothNotReady(readiness.problems));
+  }
+
   let key_id = dotenv_codegen::dotenv!("RAZORPAY_KEY_ID");
   let key_secret = dotenv_codegen::dotenv!("RAZORPAY_KEY_SECRET");
 
-  let url = "https://api.razorpay.com/v1/payments/qr_codes";
+  let url = format!("{}/payments/qr_codes", api_base());
   let client = &*RZP_CLIENT;
 
   let close_by = (Utc::now() + Duration::seconds(close_by_secs)).timestamp() as u64;
@@ -52,22 +137,48 @@ pub async fn create_qr(amount: u64, close_by_secs: i64) -> Result<RazorpayQrResp
     .json(&qr_payload)
     .send()
     .await
-    .map_err(|e| format!("Payment failed: {}", e))?;
+    .map_err(|e| Error::Payment(format!("Payment failed: {}", e)))?;
 
   if !res.status().is_success() {
-      return Err(format!("Failed to create QR code: {}", res.text().await.unwrap()));
+      return Err(Error::Payment(format!("Failed to create QR code: {}", res.text().await.unwrap_or_default())));
   }
 
-  let qr_res: RazorpayQrResponse = res.json().await.map_err(|e| format!("Parse error: {}", e))?;
+  let qr_res: RazorpayQrResponse = res.json().await.map_err(|e| Error::Payment(format!("Parse error: {}", e)))?;
+
+  // A new guest is paying, so any unprinted earlier sale is no longer theirs
+  ledger::set_current(None);
+  ledger::record(&directory, Sale::new(qr_res.id.clone(), PaymentMethod::Upi, amount, SaleStatus::Pending))
+    .map_err(Error::Storage)?;
+
   Ok(qr_res)
 }
 
 #[tauri::command(async)]
-pub async fn check_payment_status(qr_code_id: String) -> Result<bool, String> {
+pub async fn check_payment_status(app: AppHandle, qr_code_id: String) -> Result<bool, Error> {
+  let res_data = qr_status(&qr_code_id).await.map_err(|e| Error::Payment(e.to_string()))?;
+
+  if let Some(amount) = res_data.payments_amount_received {
+      if amount > 0 {
+        let directory = ledger::document_dir(&app).map_err(Error::Storage)?;
+        ledger::update(&directory, &qr_code_id, |sale| {
+          if sale.status == SaleStatus::Pending {
+            sale.status = SaleStatus::Paid;
+          }
+        }).map_err(Error::Storage)?;
+        ledger::set_current(Some(qr_code_id));
+
+        return Ok(true);
+      }
+  }
+
+  Ok(false)
+}
+
+async fn qr_status(qr_code_id: &str) -> Result<RazorpayPollingResponse, RequestError> {
   let key_id = dotenv_codegen::dotenv!("RAZORPAY_KEY_ID");
   let key_secret = dotenv_codegen::dotenv!("RAZORPAY_KEY_SECRET");
 
-  let url = format!("https://api.razorpay.com/v1/payments/qr_codes/{}", qr_code_id);
+  let url = format!("{}/payments/qr_codes/{}", api_base(), qr_code_id);
   let client = &*RZP_CLIENT;
 
   let res = client
@@ -75,19 +186,371 @@ pub async fn check_payment_status(qr_code_id: String) -> Result<bool, String> {
     .basic_auth(key_id, Some(key_secret))
     .send()
     .await
-    .map_err(|e| format!("Failed to fetch payment details: {}", e))?;
+    .map_err(|e| RequestError::Transport(format!("Failed to fetch payment details: {}", e)))?;
 
   if !res.status().is_success() {
-    return Err(format!("Failed to create QR code: {}", res.text().await.unwrap()));
+    return Err(RequestError::Api(format!("Failed to fetch payment details: {}", res.text().await.unwrap_or_default())));
   }
 
-  let res_data: RazorpayPollingResponse = res.json().await.map_err(|e| format!("Parse error: {}", e))?;
+  res.json().await.map_err(|e| RequestError::Api(format!("Parse error: {}", e)))
+}
 
-  if let Some(amount) = res_data.payments_amount_received {
-      if amount > 0 {
-        return Ok(true);
+/// Keeps checking the QR codes of pending sales in the background, so a
+/// payment made while the booth was offline is still recorded once the
+/// network is back.
+pub fn spawn_reconciler(app: AppHandle) {
+  thread::spawn(move || loop {
+    match ledger::document_dir(&app) {
+      Ok(directory) => {
+        if let Err(e) = tauri::async_runtime::block_on(reconcile(&directory)) {
+          log::warn!("Payment reconciliation paused: {}", e);
+        }
       }
+      Err(e) => log::warn!("Payment reconciliation paused: {}", e)
+    }
+
+    thread::sleep(RECONCILE_INTERVAL);
+  });
+}
+
+/// Settles the pending UPI sales whose QR has closed: paid when a payment
+/// came in, expired otherwise. QRs still open are left to the guest's own
+/// polling. Stops when Razorpay can't be reached, as the network is most
+/// likely still down; a sale Razorpay answers with an error for is noted and
+/// skipped until the next round.
+pub async fn reconcile(directory: &str) -> Result<(), String> {
+  let pending = ledger::load(directory)
+    .into_iter()
+    .filter(|sale| sale.status == SaleStatus::Pending && sale.method == PaymentMethod::Upi);
+
+  for sale in pending {
+    let status = match qr_status(&sale.id).await {
+      Ok(status) => status,
+      Err(RequestError::Transport(e)) => return Err(e),
+      Err(RequestError::Api(e)) => {
+        log::warn!("Failed to reconcile sale {}: {}", sale.id, e);
+
+        // Only note a new error, so a sale that keeps failing isn't rewritten every round
+        let note = format!("Reconciliation failed: {}", e);
+        if sale.note.as_deref() != Some(note.as_str()) {
+          ledger::update(directory, &sale.id, |sale| {
+            if sale.status == SaleStatus::Pending {
+              sale.note = Some(note);
+            }
+          })?;
+        }
+        continue;
+      }
+    };
+
+    if status.status.as_deref() != Some("closed") {
+      continue;
+    }
+    let paid = status.payments_amount_received.is_some_and(|amount| amount > 0);
+
+    ledger::update(directory, &sale.id, |sale| {
+      // The guest's own polling may have got there first
+      if sale.status != SaleStatus::Pending {
+        return;
+      }
+
+      if paid {
+        sale.status = SaleStatus::Paid;
+        sale.note = Some("Payment found after the booth stopped waiting for it".to_string());
+      } else {
+        sale.status = SaleStatus::Expired;
+      }
+    })?;
+
+    if paid {
+      log::info!("Reconciled payment for sale {}", sale.id);
+    }
   }
 
-  Ok(false)
+  Ok(())
+}
+
+/// Refunds the payment made against the QR code of sale `id` in full and
+/// records the outcome in the ledger. The sale is marked refunding under the
+/// ledger lock before Razorpay is asked, so only one refund of it goes out at
+/// a time, and one Razorpay keeps declining is left for the dashboard.
+pub async fn refund(directory: &str, id: &str, reason: &str) -> Result<Sale, String> {
+  ledger::try_update(directory, id, |sale| {
+    match sale.status {
+      SaleStatus::Paid | SaleStatus::Fulfilled => {}
+      SaleStatus::RefundFailed if sale.refund_attempts < ledger::MAX_REFUND_ATTEMPTS => {}
+      SaleStatus::RefundFailed => return Err(format!(
+        "Razorpay declined {} refunds of sale {}; refund it from the Razorpay dashboard",
+        sale.refund_attempts, id
+      )),
+      status => return Err(format!("Sale {} cannot be refunded while {:?}", id, status))
+    }
+    if sale.method != PaymentMethod::Upi {
+      return Err(format!("Sale {} was not paid through Razorpay", id));
+    }
+
+    sale.status = SaleStatus::Refunding;
+    sale.refund_attempts += 1;
+    Ok(())
+  })?;
+
+  let result = refund_payment(id, reason).await;
+
+  let updated = ledger::update(directory, id, |sale| {
+    // A refund already recorded is never undone
+    if sale.status == SaleStatus::Refunded {
+      return;
+    }
+
+    match &result {
+      Ok((payment_id, refund_id)) => {
+        sale.status = SaleStatus::Refunded;
+        sale.payment_id = Some(payment_id.clone());
+        sale.refund_id = refund_id.clone();
+        sale.note = Some(match refund_id {
+          Some(_) => reason.to_string(),
+          None => format!("{}; Razorpay had already refunded the payment", reason)
+        });
+      }
+      Err(e) => {
+        sale.status = SaleStatus::RefundFailed;
+        sale.note = Some(format!("{}; refund failed: {}", reason, e));
+      }
+    }
+  })?;
+
+  result?;
+  updated.ok_or_else(|| format!("No sale {} in the ledger", id))
+}
+
+/// Finds the captured payment on QR code `qr_code_id` and refunds it,
+/// returning the payment and refund ids. A payment Razorpay has already
+/// refunded in full, e.g. by a refund whose outcome never reached the
+/// ledger, counts as refunded without a refund id.
+async fn refund_payment(qr_code_id: &str, reason: &str) -> Result<(String, Option<String>), String> {
+  let key_id = dotenv_codegen::dotenv!("RAZORPAY_KEY_ID");
+  let key_secret = dotenv_codegen::dotenv!("RAZORPAY_KEY_SECRET");
+
+  let client = &*RZP_CLIENT;
+
+  let res = client
+    .get(format!("{}/payments/qr_codes/{}/payments", api_base(), qr_code_id))
+    .basic_auth(key_id, Some(key_secret))
+    .send()
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+  if !res.status().is_success() {
+    return Err(format!("Failed to fetch payments: {}", res.text().await.unwrap_or_default()));
+  }
+
+  let payments: RazorpayPaymentList = res.json().await.map_err(|e| format!("Parse error: {}", e))?;
+  let payment = payments
+    .items
+    .into_iter()
+    .find(|payment| matches!(payment.status.as_str(), "captured" | "refunded"))
+    .ok_or_else(|| format!("QR code {} has no captured payment", qr_code_id))?;
+
+  if payment.status == "refunded" {
+    return Ok((payment.id, None));
+  }
+
+  let refund_payload = RazorpayRefundRequest {
+    amount: payment.amount,
+    speed: "normal".to_string(),
+    notes: serde_json::json!({ "reason": reason, "qr_code_id": qr_code_id })
+  };
+
+  let res = client
+    .post(format!("{}/payments/{}/refund", api_base(), payment.id))
+    .basic_auth(key_id, Some(key_secret))
+    .json(&refund_payload)
+    .send()
+    .await
+    .map_err(|e| format!("Refund failed: {}", e))?;
+
+  if !res.status().is_success() {
+    let body = res.text().await.unwrap_or_default();
+    if body.contains(ALREADY_REFUNDED) {
+      return Ok((payment.id, None));
+    }
+
+    return Err(format!("Refund failed: {}", body));
+  }
+
+  let refund: RazorpayRefundResponse = res.json().await.map_err(|e| format!("Parse error: {}", e))?;
+
+  Ok((payment.id, Some(refund.id)))
+}
+
+/// The Razorpay API root. Debug builds and tests can point it at a mock
+/// server with `RAZORPAY_API_BASE`; release builds always use Razorpay.
+#[cfg(any(test, debug_assertions))]
+fn api_base() -> String {
+  std::env::var("RAZORPAY_API_BASE").unwrap_or_else(|_| DEFAULT_API_BASE.to_string())
+}
+
+#[cfg(not(any(test, debug_assertions)))]
+fn api_base() -> String {
+  DEFAULT_API_BASE.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use serde_json::json;
+  use tiny_http::{Method, Response, Server};
+
+  use super::*;
+
+  /// Requests the mock Razorpay has seen, as "METHOD path".
+  static REQUESTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+  /// Starts a mock Razorpay once for every test and points the booth at it.
+  /// Each QR is closed with one captured payment, except `qr_unknown`, which
+  /// Razorpay has never heard of, and `qr_settled`, whose payment is already
+  /// refunded. Refunds of `pay_qr_declined` fail, and `pay_qr_twice` has been
+  /// refunded before.
+  static MOCK: Lazy<()> = Lazy::new(|| {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+    std::env::set_var("RAZORPAY_API_BASE", format!("http://127.0.0.1:{}", port));
+
+    thread::spawn(move || {
+      for request in server.incoming_requests() {
+        let path = request.url().to_string();
+        REQUESTS.lock().unwrap().push(format!("{} {}", request.method(), path));
+
+        let segments: Vec<_> = path.trim_start_matches('/').split('/').collect();
+
+        let (status, body) = match (request.method(), segments.as_slice()) {
+          (Method::Get, ["payments", "qr_codes", "qr_unknown"]) => (400, json!({
+            "error": { "description": "The id provided does not exist" }
+          })),
+          (Method::Get, ["payments", "qr_codes", _]) => (200, json!({
+            "payments_amount_received": 100,
+            "status": "closed"
+          })),
+          (Method::Get, ["payments", "qr_codes", "qr_settled", "payments"]) => (200, json!({
+            "items": [{ "id": "pay_qr_settled", "amount": 100, "status": "refunded" }]
+          })),
+          (Method::Get, ["payments", "qr_codes", qr, "payments"]) => (200, json!({
+            "items": [
+              { "id": format!("pay_{}", qr), "amount": 100, "status": "failed" },
+              { "id": format!("pay_{}", qr), "amount": 100, "status": "captured" }
+            ]
+          })),
+          (Method::Post, ["payments", "pay_qr_declined", "refund"]) => (400, json!({
+            "error": { "description": "Your account does not have enough balance to carry out the refund operation." }
+          })),
+          (Method::Post, ["payments", "pay_qr_twice", "refund"]) => (400, json!({
+            "error": { "description": "The payment has been fully refunded already" }
+          })),
+          (Method::Post, ["payments", _, "refund"]) => (200, json!({ "id": "rfnd_1" })),
+          _ => (404, json!({}))
+        };
+        let _ = request.respond(Response::from_string(body.to_string()).with_status_code(status));
+      }
+    });
+  });
+
+  /// A ledger with a UPI sale for each QR in `ids`, in a fresh directory.
+  fn ledger_with(name: &str, ids: &[&str], status: SaleStatus) -> String {
+    let directory = std::env::temp_dir().join(format!("memorabooth-razorpay-{}", name));
+    let _ = std::fs::remove_dir_all(&directory);
+    let directory = directory.to_string_lossy().to_string();
+
+    for id in ids {
+      ledger::record(&directory, Sale::new(id.to_string(), PaymentMethod::Upi, 100, status)).unwrap();
+    }
+
+    directory
+  }
+
+  fn requested(request: &str) -> bool {
+    REQUESTS.lock().unwrap().iter().any(|seen| seen == request)
+  }
+
+  #[test]
+  fn refunds_the_captured_payment() {
+    Lazy::force(&MOCK);
+    let directory = ledger_with("refunded", &["qr_refunded"], SaleStatus::Paid);
+
+    let sale = tauri::async_runtime::block_on(refund(&directory, "qr_refunded", "Print failed")).unwrap();
+
+    assert!(requested("GET /payments/qr_codes/qr_refunded/payments"));
+    assert!(requested("POST /payments/pay_qr_refunded/refund"));
+    assert_eq!(sale.status, SaleStatus::Refunded);
+    assert_eq!(sale.payment_id.as_deref(), Some("pay_qr_refunded"));
+    assert_eq!(sale.refund_id.as_deref(), Some("rfnd_1"));
+
+    let stored = ledger::find(&directory, "qr_refunded").unwrap();
+    assert_eq!(stored.status, SaleStatus::Refunded);
+    assert_eq!(stored.note.as_deref(), Some("Print failed"));
+  }
+
+  #[test]
+  fn records_a_declined_refund() {
+    Lazy::force(&MOCK);
+    let directory = ledger_with("declined", &["qr_declined"], SaleStatus::Paid);
+
+    let result = tauri::async_runtime::block_on(refund(&directory, "qr_declined", "Print failed"));
+
+    assert!(result.unwrap_err().contains("enough balance"));
+    assert!(requested("POST /payments/pay_qr_declined/refund"));
+
+    let stored = ledger::find(&directory, "qr_declined").unwrap();
+    assert_eq!(stored.status, SaleStatus::RefundFailed);
+    assert_eq!(stored.refund_id, None);
+    assert_eq!(stored.refund_attempts, 1);
+    assert!(stored.note.unwrap().starts_with("Print failed; refund failed:"));
+  }
+
+  #[test]
+  fn an_earlier_refund_counts_as_refunded() {
+    Lazy::force(&MOCK);
+    let directory = ledger_with("twice", &["qr_twice", "qr_settled"], SaleStatus::RefundFailed);
+
+    for id in ["qr_twice", "qr_settled"] {
+      let sale = tauri::async_runtime::block_on(refund(&directory, id, "Print failed")).unwrap();
+
+      assert_eq!(sale.status, SaleStatus::Refunded);
+      assert_eq!(sale.refund_id, None);
+      assert_eq!(ledger::find(&directory, id).unwrap().status, SaleStatus::Refunded);
+    }
+    // A payment listed as refunded is not refunded again
+    assert!(!requested("POST /payments/pay_qr_settled/refund"));
+  }
+
+  #[test]
+  fn refunds_a_sale_once_at_a_time_and_stops_retrying() {
+    Lazy::force(&MOCK);
+    let directory = ledger_with("in-flight", &["qr_in_flight"], SaleStatus::Refunding);
+
+    let result = tauri::async_runtime::block_on(refund(&directory, "qr_in_flight", "Print failed"));
+    assert!(result.unwrap_err().contains("cannot be refunded while Refunding"));
+    assert!(!requested("GET /payments/qr_codes/qr_in_flight/payments"));
+
+    let directory = ledger_with("retries", &["qr_retried"], SaleStatus::RefundFailed);
+    ledger::update(&directory, "qr_retried", |sale| sale.refund_attempts = ledger::MAX_REFUND_ATTEMPTS).unwrap();
+
+    let result = tauri::async_runtime::block_on(refund(&directory, "qr_retried", "Print failed"));
+    assert!(result.unwrap_err().contains("Razorpay dashboard"));
+    assert!(!requested("GET /payments/qr_codes/qr_retried/payments"));
+    assert_eq!(ledger::find(&directory, "qr_retried").unwrap().status, SaleStatus::RefundFailed);
+  }
+
+  #[test]
+  fn reconcile_skips_sales_razorpay_rejects() {
+    Lazy::force(&MOCK);
+    let directory = ledger_with("reconcile", &["qr_unknown", "qr_reconciled"], SaleStatus::Pending);
+
+    tauri::async_runtime::block_on(reconcile(&directory)).unwrap();
+
+    let unknown = ledger::find(&directory, "qr_unknown").unwrap();
+    assert_eq!(unknown.status, SaleStatus::Pending);
+    assert!(unknown.note.unwrap().contains("does not exist"));
+    assert_eq!(ledger::find(&directory, "qr_reconciled").unwrap().status, SaleStatus::Paid);
+  }
 }
\ No newline at end of file
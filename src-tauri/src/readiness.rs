@@ -0,0 +1,177 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    camera::{self, CameraInfo, CaptureError},
+    config,
+    error::Error,
+    printer::{self, PrinterStatus},
+};
+
+/// Something that would stop the booth from delivering a paid session.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Problem {
+    CameraUnavailable { detail: String },
+    PrinterOffline { printer: String, detail: String },
+    OutOfPaper { remaining: u64, needed: u64 },
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Readiness {
+    pub ready: bool,
+    pub problems: Vec<Problem>,
+}
+
+#[tauri::command(async)]
+pub async fn booth_readiness(app: AppHandle, copies: u64) -> Result<Readiness, Error> {
+    let directory = app
+        .path()
+        .document_dir()
+        .map_err(|e| Error::Storage(format!("Failed to find documents directory: {}", e)))?
+        .to_string_lossy()
+        .to_string();
+
+    tauri::async_runtime::spawn_blocking(move || check(&directory, copies))
+        .await
+        .map_err(|e| Error::Internal(format!("Readiness check failed: {}", e)))
+}
+
+/// Checks the camera, the active printer and the paper left for `copies`
+/// prints, collecting every problem rather than stopping at the first.
+pub fn check(directory: &str, copies: u64) -> Readiness {
+    let profile = config::active_printer(directory);
+
+    assess(
+        camera::cameras(),
+        &printer::status(&profile),
+        config::paper_remaining(directory, &profile),
+        copies,
+    )
+}
+
+/// Turns what the camera, printer and page counter report into problems.
+fn assess(
+    cameras: Result<Vec<CameraInfo>, CaptureError>,
+    status: &PrinterStatus,
+    remaining: u64,
+    copies: u64,
+) -> Readiness {
+    let mut problems = Vec::new();
+
+    match cameras {
+        Ok(cameras) if cameras.is_empty() => problems.push(Problem::CameraUnavailable {
+            detail: "No camera detected".to_string(),
+        }),
+        Ok(_) => {}
+        Err(e) => problems.push(Problem::CameraUnavailable {
+            detail: e.to_string(),
+        }),
+    }
+
+    if !status.online {
+        let detail = if status.state_reasons.is_empty() {
+            status.state.clone()
+        } else {
+            status.state_reasons.join(", ")
+        };
+
+        problems.push(Problem::PrinterOffline {
+            printer: status.profile.clone(),
+            detail,
+        });
+    }
+
+    if remaining < copies {
+        problems.push(Problem::OutOfPaper {
+            remaining,
+            needed: copies,
+        });
+    }
+
+    Readiness {
+        ready: problems.is_empty(),
+        problems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> CameraInfo {
+        CameraInfo {
+            index: 0,
+            model: "imx708".to_string(),
+            width: 4608,
+            height: 2592,
+            format: "10-bit RGGB".to_string(),
+            device: "/base/axi/pcie@120000/rp1/i2c@88000/imx708@1a".to_string(),
+            modes: vec![],
+        }
+    }
+
+    fn printer(online: bool) -> PrinterStatus {
+        PrinterStatus {
+            profile: "4x6".to_string(),
+            queue: Some("DNP_DS620".to_string()),
+            online,
+            state: if online { "idle" } else { "stopped" }.to_string(),
+            accepting_jobs: online,
+            queued_jobs: 0,
+            state_reasons: vec![],
+            markers: vec![],
+        }
+    }
+
+    #[test]
+    fn ready_with_camera_printer_and_paper() {
+        let readiness = assess(Ok(vec![camera()]), &printer(true), 10, 2);
+
+        assert!(readiness.ready);
+        assert!(readiness.problems.is_empty());
+    }
+
+    #[test]
+    fn collects_every_problem() {
+        let readiness = assess(Ok(vec![]), &printer(false), 1, 2);
+
+        assert!(!readiness.ready);
+        assert!(matches!(
+            readiness.problems.as_slice(),
+            [
+                Problem::CameraUnavailable { .. },
+                Problem::PrinterOffline { .. },
+                Problem::OutOfPaper {
+                    remaining: 1,
+                    needed: 2
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn a_full_page_counter_is_not_ready() {
+        let directory = std::env::temp_dir().join("memorabooth-readiness-paper");
+        let _ = std::fs::remove_dir_all(&directory);
+        let directory = directory.to_string_lossy().to_string();
+        let profile = config::active_printer(&directory);
+
+        // Printing past the end of the pack holds the counter at its size
+        config::write_pages(directory.clone(), profile.paper_capacity - 1).unwrap();
+        config::count_pages(&directory, 3).unwrap();
+        assert_eq!(config::load_pages(&directory), profile.paper_capacity);
+
+        let remaining = config::paper_remaining(&directory, &profile);
+        let readiness = assess(Ok(vec![camera()]), &printer(true), remaining, 1);
+
+        assert!(!readiness.ready);
+        assert!(matches!(
+            readiness.problems.as_slice(),
+            [Problem::OutOfPaper {
+                remaining: 0,
+                needed: 1
+            }]
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+use std::{fs, sync::Mutex};
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{camera, error::Error, imaging::Layout};
+
+static SESSION: Lazy<Mutex<Option<CaptureSession>>> = Lazy::new(|| Mutex::new(None));
+
+/// Every take of one photo slot; retakes are kept until the session ends so
+/// the guest can still go back to an earlier one.
+#[derive(Serialize, Clone, Default)]
+pub struct Slot {
+    pub takes: Vec<String>,
+    pub selected: Option<usize>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CaptureSession {
+    pub id: String,
+    pub layout: Layout,
+    pub slots: Vec<Slot>,
+}
+
+impl CaptureSession {
+    /// The selected take of every slot, in order, once all slots are filled.
+    pub fn images(&self) -> Result<Vec<String>, Error> {
+        self.slots
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| {
+                slot.selected
+                    .map(|take| slot.takes[take].clone())
+                    .ok_or_else(|| {
+                        Error::ImageMissing(format!("Photo {} has not been taken yet", i + 1))
+                    })
+            })
+            .collect()
+    }
+
+    /// Goes back to take `take` of `slot`.
+    fn select(&mut self, slot: usize, take: usize) -> Result<(), Error> {
+        let slot_state = self
+            .slots
+            .get_mut(slot)
+            .filter(|slot_state| take < slot_state.takes.len())
+            .ok_or_else(|| {
+                Error::Invalid(format!("Photo {} has no take {}", slot + 1, take + 1))
+            })?;
+        slot_state.selected = Some(take);
+
+        Ok(())
+    }
+
+    fn discard_unselected(&self) {
+        for slot in &self.slots {
+            for (i, take) in slot.takes.iter().enumerate() {
+                if Some(i) != slot.selected {
+                    if let Err(e) = fs::remove_file(take) {
+                        log::warn!("Failed to delete discarded frame {}: {}", take, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The selected takes of capture session `id`, which has to be the one in
+/// progress and taken for `layout`.
+pub fn selected_images(id: &str, layout: &Layout) -> Result<Vec<String>, Error> {
+    match SESSION.lock().unwrap().as_ref() {
+        Some(session) if session.id == id && session.layout == *layout => session.images(),
+        Some(session) if session.id == id => Err(Error::Invalid(format!(
+            "Capture session {} was taken for Layout {:?}",
+            id, session.layout
+        ))),
+        _ => Err(Error::Invalid(format!(
+            "Capture session {} is no longer in progress",
+            id
+        ))),
+    }
+}
+
+/// Ends capture session `id` once its print has been submitted, leaving any
+/// session started since alone.
+pub fn finish(id: &str) {
+    let mut guard = SESSION.lock().unwrap();
+
+    if guard.as_ref().is_some_and(|session| session.id == id) {
+        if let Some(session) = guard.take() {
+            session.discard_unselected();
+        }
+    }
+}
+
+/// The id of the capture session in progress, if any.
+pub fn current_id() -> Option<String> {
+    SESSION
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|session| session.id.clone())
+}
+
+#[tauri::command]
+pub fn start_session(layout: Layout) -> Result<CaptureSession, Error> {
+    let session = CaptureSession {
+        id: format!("{:x}", Utc::now().timestamp_millis()),
+        slots: vec![Slot::default(); layout.slots()],
+        layout,
+    };
+
+    if let Some(previous) = SESSION.lock().unwrap().replace(session.clone()) {
+        previous.discard_unselected();
+    }
+
+    Ok(session)
+}
+
+/// Takes a photo for `slot`, keeping any earlier takes of it.
+#[tauri::command(async)]
+pub async fn capture_slot(app: AppHandle, slot: usize) -> Result<CaptureSession, Error> {
+    let (session_id, take) = {
+        let guard = SESSION.lock().unwrap();
+        let session = guard
+            .as_ref()
+            .ok_or_else(|| Error::Invalid("No capture session in progress".to_string()))?;
+
+        let slot_state = session.slots.get(slot).ok_or_else(|| {
+            Error::Invalid(format!(
+                "Layout {:?} has no photo {}",
+                session.layout,
+                slot + 1
+            ))
+        })?;
+
+        (session.id.clone(), slot_state.takes.len())
+    };
+
+    let pictures = app
+        .path()
+        .picture_dir()
+        .map_err(|e| Error::Storage(format!("Failed to find pictures directory: {}", e)))?;
+    let output_path = pictures
+        .join(format!(
+            "photo-{}-{}-{}.jpg",
+            session_id,
+            slot + 1,
+            take + 1
+        ))
+        .to_string_lossy()
+        .to_string();
+
+    let path = camera::capture(app.clone(), output_path).await?;
+
+    let mut guard = SESSION.lock().unwrap();
+    let session = guard
+        .as_mut()
+        .filter(|session| session.id == session_id)
+        .ok_or_else(|| Error::Invalid("Capture session ended during capture".to_string()))?;
+
+    let slot_state = &mut session.slots[slot];
+    slot_state.takes.push(path);
+    slot_state.selected = Some(slot_state.takes.len() - 1);
+
+    Ok(session.clone())
+}
+
+/// Goes back to an earlier take of `slot`.
+#[tauri::command]
+pub fn select_take(slot: usize, take: usize) -> Result<CaptureSession, Error> {
+    let mut guard = SESSION.lock().unwrap();
+    let session = guard
+        .as_mut()
+        .ok_or_else(|| Error::Invalid("No capture session in progress".to_string()))?;
+    session.select(slot, take)?;
+
+    Ok(session.clone())
+}
+
+#[tauri::command]
+pub fn get_session() -> Option<CaptureSession> {
+    SESSION.lock().unwrap().clone()
+}
+
+/// Ends the session, deleting every take the guest did not keep.
+#[tauri::command]
+pub fn end_session() -> Result<(), Error> {
+    if let Some(session) = SESSION.lock().unwrap().take() {
+        session.discard_unselected();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// A session whose slots hold `takes` files each in a fresh directory,
+    /// with the latest take selected.
+    fn session(name: &str, layout: Layout, takes: usize) -> CaptureSession {
+        let directory = std::env::temp_dir().join(format!("memorabooth-session-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let slots = (0..layout.slots())
+            .map(|slot| {
+                let takes: Vec<_> = (0..takes)
+                    .map(|take| {
+                        let path = directory.join(format!("photo-{}-{}.jpg", slot + 1, take + 1));
+                        fs::write(&path, b"jpeg").unwrap();
+                        path.to_string_lossy().to_string()
+                    })
+                    .collect();
+
+                Slot {
+                    selected: takes.len().checked_sub(1),
+                    takes,
+                }
+            })
+            .collect();
+
+        CaptureSession {
+            id: name.to_string(),
+            layout,
+            slots,
+        }
+    }
+
+    fn name(path: &str) -> &str {
+        Path::new(path).file_name().unwrap().to_str().unwrap()
+    }
+
+    #[test]
+    fn images_are_the_selected_takes_in_slot_order() {
+        let mut session = session("images", Layout::B, 2);
+        session.select(2, 0).unwrap();
+
+        let images = session.images().unwrap();
+        let names: Vec<_> = images.iter().map(|path| name(path)).collect();
+
+        assert_eq!(
+            names,
+            [
+                "photo-1-2.jpg",
+                "photo-2-2.jpg",
+                "photo-3-1.jpg",
+                "photo-4-2.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn images_need_every_slot_taken() {
+        let mut session = session("missing", Layout::A, 1);
+        session.slots[1] = Slot::default();
+
+        match session.images() {
+            Err(Error::ImageMissing(message)) => {
+                assert_eq!(message, "Photo 2 has not been taken yet")
+            }
+            other => panic!("expected a missing photo, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn selects_only_takes_that_exist() {
+        let mut session = session("select", Layout::A, 2);
+
+        session.select(0, 0).unwrap();
+        assert_eq!(session.slots[0].selected, Some(0));
+
+        assert!(matches!(session.select(0, 2), Err(Error::Invalid(_))));
+        assert!(matches!(session.select(2, 0), Err(Error::Invalid(_))));
+        assert_eq!(session.slots[0].selected, Some(0));
+    }
+
+    #[test]
+    fn discards_only_the_takes_not_kept() {
+        let mut session = session("discard", Layout::A, 3);
+        session.select(1, 0).unwrap();
+        let takes: Vec<_> = session
+            .slots
+            .iter()
+            .flat_map(|slot| slot.takes.clone())
+            .collect();
+        let kept = session.images().unwrap();
+
+        session.discard_unselected();
+
+        for take in &takes {
+            assert_eq!(
+                Path::new(take).exists(),
+                kept.contains(take),
+                "{}",
+                name(take)
+            );
+        }
+    }
+}
@@ -8,17 +8,23 @@ import reset from './Utils/reset'
 const Home = React.lazy(() => import('./Pages/Home'))
 const Mail = React.lazy(() => import('./Pages/Mail'))
 const Greeting = React.lazy(() => import('./Pages/Greeting'))
+const Preview = React.lazy(() => import('./Pages/Preview'))
 const Admin = React.lazy(() => import('./Pages/Admin'))
 const AdminMode = React.lazy(() => import('./Pages/Admin/Mode'))
 const AdminConfig = React.lazy(() => import('./Pages/Admin/Config'))
 const AdminLayouts = React.lazy(() => import('./Pages/Admin/Layouts'))
 const AdminPages = React.lazy(() => import('./Pages/Admin/Pages'))
+const AdminPrinters = React.lazy(() => import('./Pages/Admin/Printers'))
+const AdminCamera = React.lazy(() => import('./Pages/Admin/Camera'))
+const AdminSales = React.lazy(() => import('./Pages/Admin/Sales'))
+const AdminSystem = React.lazy(() => import('./Pages/Admin/System'))
 const Countdown = React.lazy(() => import('./Pages/Countdown'))
 const Passcode = React.lazy(() => import('./Pages/Passcode'))
 const Layout = React.lazy(() => import('./Pages/Form/Layout'))
 const Copies = React.lazy(() => import('./Pages/Form/Copies'))
 const Print = React.lazy(() => import('./Pages/Form/Print'))
 const Payment = React.lazy(() => import('./Pages/Form/Payment'))
+const Cash = React.lazy(() => import('./Pages/Form/Cash'))
 
 export default function AnimatedRoutes() {
     const location = useLocation()
@@ -32,6 +38,7 @@ export default function AnimatedRoutes() {
                     <Routes location={location} key={location.pathname}>
                         <Route path='/' element={<Home />} />
                         <Route path='/mail' element={<Mail />} />
+                        <Route path='/preview' element={<Preview />} />
                         <Route path='/greeting' element={<Greeting />} />
                         <Route path='/passcode' element={<Passcode />} />
                         <Route path='/admin' element={<Admin />}>
@@ -39,12 +46,17 @@ export default function AnimatedRoutes() {
                             <Route path='config' element={<AdminConfig />} />
                             <Route path='layouts' element={<AdminLayouts />} />
                             <Route path='pages' element={<AdminPages />} />
+                            <Route path='printers' element={<AdminPrinters />} />
+                            <Route path='camera' element={<AdminCamera />} />
+                            <Route path='sales' element={<AdminSales />} />
+                            <Route path='system' element={<AdminSystem />} />
                         </Route>
                         <Route path='/countdown' element={<Countdown />} />
                         <Route path='/layout' element={<Layout />} />
                         <Route path='/copies' element={<Copies />} />
                         <Route path='/print' element={<Print />} />
                         <Route path='/payment' element={<Payment />} />
+                        <Route path='/cash' element={<Cash />} />
                     </Routes>
                 </DataProvider>
             </AnimatePresence>
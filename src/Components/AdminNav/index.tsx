@@ -33,6 +33,34 @@ export default function AdminNav() {
         >
           Pages
         </NavLink>
+        <NavLink
+            to="/admin/printers"
+            className="admin-nav-link"
+            style={({ isActive }) => ({ pointerEvents: isActive ? 'none' : 'auto' })}
+        >
+          Printers
+        </NavLink>
+        <NavLink
+            to="/admin/camera"
+            className="admin-nav-link"
+            style={({ isActive }) => ({ pointerEvents: isActive ? 'none' : 'auto' })}
+        >
+          Camera
+        </NavLink>
+        <NavLink
+            to="/admin/sales"
+            className="admin-nav-link"
+            style={({ isActive }) => ({ pointerEvents: isActive ? 'none' : 'auto' })}
+        >
+          Sales
+        </NavLink>
+        <NavLink
+            to="/admin/system"
+            className="admin-nav-link"
+            style={({ isActive }) => ({ pointerEvents: isActive ? 'none' : 'auto' })}
+        >
+          System
+        </NavLink>
     </nav>
   )
 }
@@ -1,5 +1,4 @@
 import React from 'react'
-import { useNavigate } from 'react-router-dom'
 
 import clear from '../../assets/Images/clear.svg'
 import backspace from '../../assets/Images/backspace.svg'
@@ -7,18 +6,15 @@ import backspace from '../../assets/Images/backspace.svg'
 import './styles.css'
 
 export default function Numpad({
-    correct,
     code,
     setCode,
-    setStatus
+    onEnter
 }: {
-    correct: string,
     code: Array<string>,
     setCode: React.Dispatch<React.SetStateAction<Array<string>>>,
-    setStatus: React.Dispatch<React.SetStateAction<number>>
+    // Called with the full code once the last digit is entered
+    onEnter: (code: string) => void
 }) {
-	const navigate = useNavigate()
-
     function handleNumPress(num: string): void {
         const nextIndex = code.findIndex((digit) => digit === "")
         if (nextIndex === -1) return
@@ -27,20 +23,7 @@ export default function Numpad({
         updatedCode[nextIndex] = num
         setCode(updatedCode)
 
-        if (nextIndex === 3 && updatedCode.join("") === correct) {
-            setStatus(1)
-			setTimeout(() => {
-				navigate("/admin/mode")
-			}, 1000);
-        }
-
-        if (nextIndex === 3 && updatedCode.join("") !== correct) {
-			setStatus(0)
-			setTimeout(() => {
-				setStatus(-1)
-				setCode(["", "", "", ""])
-			}, 1000);
-        }
+        if (nextIndex === 3) onEnter(updatedCode.join(""))
     }
 
     function handleBackspace(): void {
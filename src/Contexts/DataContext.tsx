@@ -1,10 +1,19 @@
 import React, { createContext, useContext, useEffect, useMemo, useState } from "react"
+import { listen } from "@tauri-apps/api/event"
 import { getOrInitConfig, getOrInitLayouts, getOrInitPages } from "../Services/commands"
-import { Addon, Layout, LayoutData, Mode, Options, Plan } from "../types"
+import { Addon, AnimationMode, Attendant, LogLevel, Layout, LayoutData, Mode, Options, Plan } from "../types"
 
 export interface Config {
     plans: Plan[],
-    digital: Addon
+    digital: Addon,
+    event_name?: string,
+    booth_id?: string,
+    branding?: string | null,
+    smart_crop?: boolean,
+    animation?: AnimationMode | null,
+    capture_timeout?: number,
+    attendants?: Attendant[],
+    log_level?: LogLevel
 }
 
 interface DataContextProps {
@@ -23,6 +32,9 @@ interface DataContextProps {
     images: Array<string>
     setImages: React.Dispatch<React.SetStateAction<Array<string>>>,
 
+    animation: string | null,
+    setAnimation: React.Dispatch<React.SetStateAction<string | null>>,
+
     pages: number,
     setPages: React.Dispatch<React.SetStateAction<number>>
 }
@@ -45,6 +57,7 @@ export default function DataProvider({ children }: { children: React.ReactNode }
     })
     const [mode, setMode] = useState<Mode>(Mode.AUTOMATIC)
     const [images, setImages] = useState<Array<string>>([]);
+    const [animation, setAnimation] = useState<string | null>(null);
     const [config, setConfig] = useState<Config>({
         plans: [],
         digital: { enabled: false, price: 0, title: "Digital Copy" }
@@ -120,6 +133,12 @@ export default function DataProvider({ children }: { children: React.ReactNode }
         }
 
         fetch()
+
+        const unlisten = listen("config-changed", fetch)
+
+        return () => {
+            unlisten.then(fn => fn())
+        }
     }, [])
 
     const value = {
@@ -133,6 +152,8 @@ export default function DataProvider({ children }: { children: React.ReactNode }
         setMode,
         images,
         setImages,
+        animation,
+        setAnimation,
         pages,
         setPages
     }
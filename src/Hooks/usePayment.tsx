@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useCallback, useRef, useState } from "react";
+import { CommandError, ReadinessProblem } from "../types";
 
 interface QrResponse {
     id: string,
@@ -7,20 +8,30 @@ interface QrResponse {
     close_by: number
 }
 
+function describeProblem(problem: ReadinessProblem) {
+    switch (problem.reason) {
+        case "camera_unavailable": return `camera unavailable (${problem.detail})`
+        case "printer_offline": return `printer ${problem.printer} offline (${problem.detail})`
+        case "out_of_paper": return `${problem.remaining} prints of paper left`
+    }
+}
+
 export default function usePayment() {
     const [qrCode, setQrCode] = useState<QrResponse | undefined>(undefined)
     const [loading, setLoading] = useState<boolean>(false)
     const [error, setError] = useState<string | null>(null)
+    const [notReady, setNotReady] = useState<boolean>(false)
     const [paid, setPaid] = useState<boolean | null>(null)
 
     const pollingIntervalRef = useRef<NodeJS.Timeout | null>(null)
 
-    const fetchQrCode = useCallback(async (amt: number) => {
+    const fetchQrCode = useCallback(async (amt: number, copies: number) => {
         setLoading(true)
         setError(null)
+        setNotReady(false)
 
         try {
-            const res = await invoke<QrResponse>('create_qr', { amount: amt, closeBySecs: 180 })
+            const res = await invoke<QrResponse>('create_qr', { amount: amt, closeBySecs: 180, copies })
             setQrCode(res)
 
             let img = new Image()
@@ -32,7 +43,11 @@ export default function usePayment() {
             return res
         } catch (err) {
             console.error("Error fetching QR Code:", err)
-            setError("Failed to fetch QR Code")
+            const qrError = err as CommandError
+            setNotReady(qrError.code == "booth_not_ready")
+            setError(qrError.code == "booth_not_ready"
+                ? `Booth not ready: ${qrError.details.map(describeProblem).join(", ")}`
+                : qrError.message ?? "Failed to fetch QR Code")
             return undefined
         } finally {
             setLoading(false)
@@ -67,5 +82,5 @@ export default function usePayment() {
         pollingIntervalRef.current = interval
     }
 
-    return { qrCode, loading, error, fetchQrCode, paid, pollingIntervalRef }
+    return { qrCode, loading, error, notReady, fetchQrCode, paid, pollingIntervalRef }
 }
\ No newline at end of file
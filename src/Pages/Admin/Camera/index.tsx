@@ -0,0 +1,106 @@
+import { motion } from 'framer-motion'
+import { useEffect, useState } from 'react'
+
+import { calibrateExposure, cameraSelfTest, listCameras } from '../../../Services/commands'
+import { Calibration, CameraInfo, SelfTestReport } from '../../../types'
+import errorMessage from '../../../Utils/error'
+
+import './styles.css'
+
+export default function Camera() {
+  const [cameras, setCameras] = useState<CameraInfo[] | null>(null)
+  const [report, setReport] = useState<SelfTestReport | null>(null)
+  const [calibration, setCalibration] = useState<Calibration | null>(null)
+  const [testing, setTesting] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    listCameras()
+      .then(setCameras)
+      .catch(e => setError(errorMessage(e)))
+  }, [])
+
+  async function handleTest() {
+    setTesting(true)
+    setError(null)
+
+    try {
+      setReport(await cameraSelfTest())
+    } catch (e) {
+      setReport(null)
+      setError(errorMessage(e))
+    } finally {
+      setTesting(false)
+    }
+  }
+
+  async function handleCalibrate() {
+    setTesting(true)
+    setError(null)
+
+    try {
+      setCalibration(await calibrateExposure())
+    } catch (e) {
+      setCalibration(null)
+      setError(errorMessage(e))
+    } finally {
+      setTesting(false)
+    }
+  }
+
+  return (
+    <motion.div
+      id="admin-camera"
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+    >
+      <h1 className="heading">
+        Check the <div>Camera</div>
+      </h1>
+
+      <div className="camera-list">
+        {cameras?.length === 0 && <div className="camera-card">No camera detected</div>}
+        {cameras?.map(camera => (
+          <div key={camera.index} className="camera-card">
+            <div className="camera-model">{camera.model}</div>
+            <div>{camera.width}x{camera.height} {camera.format}</div>
+            {camera.modes.map(mode => (
+              <div key={`${mode.width}x${mode.height}`} className="camera-mode">
+                {mode.width}x{mode.height} @ {mode.fps.toFixed(0)} fps
+              </div>
+            ))}
+          </div>
+        ))}
+      </div>
+
+      <div className="camera-actions">
+        <button className="test-btn" onClick={handleTest} disabled={testing}>
+          {testing ? "Testing..." : "Take test photo"}
+        </button>
+        <button className="test-btn" onClick={handleCalibrate} disabled={testing}>
+          Calibrate exposure
+        </button>
+      </div>
+
+      {report && (
+        <div className="camera-card">
+          <div>{report.width}x{report.height} in {report.capture_ms} ms</div>
+          <div>Brightness {report.mean_brightness.toFixed(0)} / 255</div>
+          <div>Shadows clipped {report.clipped_shadows.toFixed(1)}%</div>
+          <div>Highlights clipped {report.clipped_highlights.toFixed(1)}%</div>
+        </div>
+      )}
+
+      {calibration && (
+        <div className="camera-card">
+          <div>{calibration.converged ? "Exposure calibrated" : "Closest exposure the camera allows"}</div>
+          <div>Shutter {calibration.settings.shutter} µs, gain {calibration.settings.gain}</div>
+          <div>Brightness {calibration.exposure.mean_brightness.toFixed(0)} / 255 after {calibration.iterations} shots</div>
+        </div>
+      )}
+
+      {error && <div className="camera-error">{error}</div>}
+    </motion.div>
+  )
+}
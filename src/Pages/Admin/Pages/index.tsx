@@ -2,24 +2,53 @@ import { motion } from 'framer-motion'
 import { useEffect, useState } from 'react'
 
 import { useData } from '../../../Contexts/DataContext'
-import { savePages } from '../../../Services/commands'
+import { printerStatus, printTestPage, savePages } from '../../../Services/commands'
+import { Layout, PrinterStatus } from '../../../types'
+import errorMessage from '../../../Utils/error'
 
 import './styles.css'
 
 export default function Pages() {
   const { pages, setPages } = useData()
   const [localPages, setLocalPages] = useState<number>(pages)
+  const [printers, setPrinters] = useState<PrinterStatus[]>([])
+  const [error, setError] = useState<string | null>(null)
+
+  async function refreshStatus() {
+    try {
+      setPrinters(await printerStatus())
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  useEffect(() => {
+    refreshStatus()
+  }, [])
+
+  async function handleTestPage() {
+    setError(null)
+
+    try {
+      await printTestPage(Layout.B)
+      await refreshStatus()
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
 
   useEffect(() => {
     setLocalPages(pages)
   }, [pages])
 
   async function handleSave() {
+    setError(null)
+
     try {
       await savePages(localPages)
       setPages(localPages)
     } catch (e) {
-      console.error(e)
+      setError(errorMessage(e))
     }
   }
 
@@ -39,9 +68,8 @@ export default function Pages() {
           <input
             type="number"
             value={localPages}
-            onChange={(e) => setLocalPages(Number(e.target.value) > 699 ? 699 : Number(e.target.value))}
+            onChange={(e) => setLocalPages(Math.max(0, Number(e.target.value)))}
             className="page-number-input"
-            max={699}
             min={0}
           />
           <button
@@ -51,6 +79,30 @@ export default function Pages() {
             Reset
           </button>
         </div>
+
+        <div className="printer-list">
+          {printers.map(printer => (
+            <div key={printer.profile} className="printer-card" data-online={printer.online}>
+              <div className="printer-name">
+                {printer.profile} {printer.queue && `(${printer.queue})`}: {printer.online ? "Online" : "Offline"}
+              </div>
+              <div>{printer.state}, {printer.queued_jobs} job(s) queued</div>
+              {printer.state_reasons.length > 0 && <div>{printer.state_reasons.join(", ")}</div>}
+              {printer.markers.map(marker => (
+                <div key={marker.name}>
+                  {marker.name}: {marker.level === null ? "unknown" : `${marker.level}%`}
+                </div>
+              ))}
+            </div>
+          ))}
+        </div>
+
+        <div className="printer-actions">
+          <button className='reset-btn' onClick={refreshStatus}>Refresh</button>
+          <button className='reset-btn' onClick={handleTestPage}>Print test page</button>
+        </div>
+
+        {error && <div className="printer-error">{error}</div>}
       </motion.div>
 
       {pages !== localPages && (
@@ -0,0 +1,232 @@
+import { motion } from 'framer-motion'
+import { useEffect, useState } from 'react'
+
+import { getOrInitPrinters, savePrinters } from '../../../Services/commands'
+import { PrinterProfile, Printers as PrinterSettings, RenderingIntent } from '../../../types'
+import errorMessage from '../../../Utils/error'
+
+import './styles.css'
+
+// Matches the profile the backend falls back to when none are saved
+const DEFAULT_PROFILE: PrinterProfile = {
+  name: "4x6",
+  queue: null,
+  paper_width: 4,
+  paper_height: 6,
+  dpi: 300,
+  margin: 0,
+  bleed: 0,
+  media: "w288h432",
+  cuts_strips: true,
+  strip_media: null,
+  paper_capacity: 700
+}
+
+const DEFAULT_PRINTERS: PrinterSettings = {
+  active: DEFAULT_PROFILE.name,
+  profiles: [DEFAULT_PROFILE]
+}
+
+const INTENTS: { intent: RenderingIntent, label: string }[] = [
+  { intent: "Perceptual", label: "Perceptual" },
+  { intent: "RelativeColorimetric", label: "Relative colorimetric" },
+  { intent: "Saturation", label: "Saturation" },
+  { intent: "AbsoluteColorimetric", label: "Absolute colorimetric" },
+]
+
+type NumberKey = "paper_width" | "paper_height" | "dpi" | "margin" | "bleed" | "paper_capacity"
+
+const NUMBER_FIELDS: { key: NumberKey, label: string, step: number }[] = [
+  { key: "paper_width", label: "Paper width (in)", step: 0.1 },
+  { key: "paper_height", label: "Paper height (in)", step: 0.1 },
+  { key: "dpi", label: "DPI", step: 1 },
+  { key: "margin", label: "Margin (in)", step: 0.01 },
+  { key: "bleed", label: "Bleed (in)", step: 0.01 },
+  { key: "paper_capacity", label: "Prints per pack", step: 1 },
+]
+
+export default function Printers() {
+  const [printers, setPrinters] = useState<PrinterSettings>(DEFAULT_PRINTERS)
+  const [selected, setSelected] = useState(0)
+  const [saved, setSaved] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    getOrInitPrinters(DEFAULT_PRINTERS)
+      .then(loaded => {
+        setPrinters(loaded)
+        setSelected(Math.max(0, loaded.profiles.findIndex(profile => profile.name == loaded.active)))
+      })
+      .catch(e => setError(errorMessage(e)))
+  }, [])
+
+  const profile = printers.profiles[selected]
+
+  function update(changes: Partial<PrinterProfile>) {
+    const profiles = printers.profiles.map((p, i) => i == selected ? { ...p, ...changes } : p)
+    // Keep the active profile pointing at the same entry through a rename
+    const active = changes.name !== undefined && printers.active == profile.name ? changes.name : printers.active
+
+    setPrinters({ active, profiles })
+    setSaved(false)
+  }
+
+  function handleAdd() {
+    const name = `Printer ${printers.profiles.length + 1}`
+
+    setPrinters({ ...printers, profiles: [...printers.profiles, { ...DEFAULT_PROFILE, name }] })
+    setSelected(printers.profiles.length)
+    setSaved(false)
+  }
+
+  function handleRemove() {
+    const profiles = printers.profiles.filter((_, i) => i != selected)
+    const active = printers.active == profile.name ? profiles[0].name : printers.active
+
+    setPrinters({ active, profiles })
+    setSelected(0)
+    setSaved(false)
+  }
+
+  async function handleSave() {
+    setError(null)
+    setSaved(false)
+
+    try {
+      await savePrinters(printers)
+      setSaved(true)
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  return (
+    <motion.div
+      id="admin-printers"
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+    >
+      <h1 className="heading">
+        Printer <div>Profiles</div>
+      </h1>
+
+      <div className="profile-tabs">
+        {printers.profiles.map((p, i) => (
+          <button
+            key={i}
+            className="profile-tab"
+            data-selected={i == selected}
+            onClick={() => setSelected(i)}
+          >
+            {p.name}{printers.active == p.name && " (active)"}
+          </button>
+        ))}
+        <button className="profile-tab" onClick={handleAdd}>+ Add</button>
+      </div>
+
+      {profile && (
+        <div className="profile-card">
+          <div className="profile-fields">
+            <label>
+              Name
+              <input value={profile.name} onChange={e => update({ name: e.target.value })} />
+            </label>
+            <label>
+              CUPS queue
+              <input
+                placeholder="System default"
+                value={profile.queue ?? ""}
+                onChange={e => update({ queue: e.target.value.trim() || null })}
+              />
+            </label>
+            <label>
+              Media
+              <input value={profile.media} onChange={e => update({ media: e.target.value.trim() })} />
+            </label>
+          </div>
+
+          <div className="profile-fields">
+            {NUMBER_FIELDS.map(field => (
+              <label key={field.key}>
+                {field.label}
+                <input
+                  type="number"
+                  min={0}
+                  step={field.step}
+                  value={profile[field.key] ?? DEFAULT_PROFILE[field.key]}
+                  onChange={e => update({ [field.key]: Number(e.target.value) } as Partial<PrinterProfile>)}
+                />
+              </label>
+            ))}
+          </div>
+
+          <div className="profile-fields">
+            <label>
+              <input
+                type="checkbox"
+                checked={profile.cuts_strips}
+                onChange={e => update({ cuts_strips: e.target.checked })}
+              />
+              Cuts 2-inch strips
+            </label>
+            <label>
+              Strip media
+              <input
+                placeholder="Queue default"
+                value={profile.strip_media ?? ""}
+                onChange={e => update({ strip_media: e.target.value.trim() || null })}
+              />
+            </label>
+          </div>
+
+          <div className="profile-fields">
+            <label>
+              ICC profile
+              <input
+                className="icc-input"
+                placeholder="None, prints are sent as sRGB"
+                value={profile.icc_profile ?? ""}
+                onChange={e => update({ icc_profile: e.target.value.trim() || null })}
+              />
+            </label>
+            <label>
+              Intent
+              <select
+                value={profile.rendering_intent ?? "Perceptual"}
+                onChange={e => update({ rendering_intent: e.target.value as RenderingIntent })}
+                disabled={!profile.icc_profile}
+              >
+                {INTENTS.map(({ intent, label }) => <option key={intent} value={intent}>{label}</option>)}
+              </select>
+            </label>
+          </div>
+
+          <div className="profile-actions">
+            <button
+              className="profile-btn"
+              onClick={() => setPrinters({ ...printers, active: profile.name })}
+              disabled={printers.active == profile.name}
+            >
+              Use for prints
+            </button>
+            <button
+              className="profile-btn"
+              onClick={handleRemove}
+              disabled={printers.profiles.length < 2}
+            >
+              Remove
+            </button>
+          </div>
+        </div>
+      )}
+
+      <div className="profile-actions">
+        <button className="profile-btn" onClick={handleSave}>Save</button>
+      </div>
+
+      {saved && <div className="profile-note">Saved</div>}
+      {error && <div className="profile-error">{error}</div>}
+    </motion.div>
+  )
+}
@@ -0,0 +1,125 @@
+import { motion } from 'framer-motion'
+import { useEffect, useState } from 'react'
+
+import { fulfilSale, getLedger, refundSale, salesReport, unfulfilledSales } from '../../../Services/commands'
+import { Revenue, Sale, SalesReport } from '../../../types'
+import errorMessage from '../../../Utils/error'
+
+import './styles.css'
+
+const REFUNDABLE = ["paid", "fulfilled", "refund_failed"]
+
+export default function Sales() {
+  const [sales, setSales] = useState<Sale[]>([])
+  const [report, setReport] = useState<SalesReport | null>(null)
+  const [unfulfilled, setUnfulfilled] = useState<Sale[]>([])
+  const [error, setError] = useState<string | null>(null)
+
+  async function refresh() {
+    try {
+      setSales((await getLedger()).reverse())
+      setReport(await salesReport())
+      setUnfulfilled(await unfulfilledSales())
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  useEffect(() => {
+    refresh()
+  }, [])
+
+  async function handleRefund(sale: Sale) {
+    setError(null)
+
+    try {
+      await refundSale(sale.id, "Refunded by attendant")
+    } catch (e) {
+      setError(errorMessage(e))
+    } finally {
+      await refresh()
+    }
+  }
+
+  async function handleFulfil(sale: Sale) {
+    setError(null)
+
+    try {
+      await fulfilSale(sale.id)
+    } catch (e) {
+      setError(errorMessage(e))
+    } finally {
+      await refresh()
+    }
+  }
+
+  return (
+    <motion.div
+      id="admin-sales"
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+    >
+      <h1 className="heading">
+        Review <div>Sales</div>
+      </h1>
+
+      {error && <div className="sales-error">{error}</div>}
+
+      {report && (
+        <div className="sales-report">
+          <RevenueCard title="UPI" revenue={report.upi} />
+          <RevenueCard title="Cash" revenue={report.cash} />
+          {Object.entries(report.attendants).map(([attendant, revenue]) => (
+            <RevenueCard key={attendant} title={`Cash · ${attendant}`} revenue={revenue} />
+          ))}
+          <RevenueCard title="Refunded" revenue={report.refunded} />
+        </div>
+      )}
+
+      {unfulfilled.length > 0 && (
+        <div className="unfulfilled">
+          <div className="unfulfilled-title">Paid but not printed</div>
+          {unfulfilled.map(sale => (
+            <div key={sale.id} className="sale-row">
+              <div>{new Date(sale.created_at * 1000).toLocaleString()}</div>
+              <div>{sale.method.toUpperCase()}</div>
+              <div>₹{sale.amount / 100}</div>
+              <div className="sale-status" data-status={sale.status}>{sale.status}</div>
+              <div className="sale-note">{sale.note}</div>
+              <div className="unfulfilled-actions">
+                <button className="fulfil-btn" onClick={() => handleFulfil(sale)}>Fulfilled</button>
+                <button className="refund-btn" onClick={() => handleRefund(sale)}>Refund</button>
+              </div>
+            </div>
+          ))}
+        </div>
+      )}
+
+      <div className="sales-list">
+        {sales.map(sale => (
+          <div key={sale.id} className="sale-row">
+            <div>{new Date(sale.created_at * 1000).toLocaleString()}</div>
+            <div>{sale.method.toUpperCase()}{sale.attendant && ` · ${sale.attendant}`}</div>
+            <div>₹{sale.amount / 100}</div>
+            <div className="sale-status" data-status={sale.status}>{sale.status.replace("_", " ")}</div>
+            <div className="sale-note">{sale.note}</div>
+            {REFUNDABLE.includes(sale.status) && (
+              <button className="refund-btn" onClick={() => handleRefund(sale)}>Refund</button>
+            )}
+          </div>
+        ))}
+      </div>
+    </motion.div>
+  )
+}
+
+function RevenueCard({ title, revenue }: { title: string, revenue: Revenue }) {
+  return (
+    <div className="revenue-card">
+      <div className="revenue-title">{title}</div>
+      <div className="revenue-amount">₹{revenue.amount / 100}</div>
+      <div className="revenue-sales">{revenue.sales} {revenue.sales === 1 ? 'sale' : 'sales'}</div>
+    </div>
+  )
+}
@@ -0,0 +1,351 @@
+import { motion } from 'framer-motion'
+import { useEffect, useState } from 'react'
+import { useLocation } from 'react-router-dom'
+
+import { useData } from '../../../Contexts/DataContext'
+import { changeAdminPin, exportDiagnostics, exportLogs, fleetStatus, getOrInitAdminApiSettings, getOrInitFleetSettings, saveAdminApiSettings, saveConfig, saveFleetSettings, syncFleet } from '../../../Services/commands'
+import { AdminApiSettings, FleetSettings, FleetState, LogLevel } from '../../../types'
+import errorMessage from '../../../Utils/error'
+
+import './styles.css'
+
+const LEVELS: LogLevel[] = ["error", "warn", "info", "debug", "trace"]
+
+const DEFAULT_FLEET: FleetSettings = {
+  enabled: false,
+  endpoint: "",
+  public_key: "",
+  token: null,
+  interval_secs: 300
+}
+
+const DEFAULT_ADMIN_API: AdminApiSettings = {
+  enabled: false,
+  address: "127.0.0.1",
+  port: 8787,
+  token: ""
+}
+
+function formatTime(secs: number | null) {
+  return secs ? new Date(secs * 1000).toLocaleString() : "never"
+}
+
+export default function System() {
+  const { config, setConfig } = useData()
+  const location = useLocation()
+  const [days, setDays] = useState<number>(7)
+  const [destination, setDestination] = useState<string>("")
+  const [exporting, setExporting] = useState(false)
+  const [result, setResult] = useState<string | null>(null)
+  const [error, setError] = useState<string | null>(null)
+  const [fleet, setFleet] = useState<FleetSettings>(DEFAULT_FLEET)
+  const [fleetState, setFleetState] = useState<FleetState | null>(null)
+  const [syncing, setSyncing] = useState(false)
+  const [adminApi, setAdminApi] = useState<AdminApiSettings>(DEFAULT_ADMIN_API)
+  const [adminApiSaved, setAdminApiSaved] = useState(false)
+  const [currentPin, setCurrentPin] = useState("")
+  const [newPin, setNewPin] = useState("")
+  const [pinChanged, setPinChanged] = useState(false)
+  const [mustChangePin, setMustChangePin] = useState<boolean>(location.state?.mustChangePin ?? false)
+
+  useEffect(() => {
+    // Nothing else opens until the default PIN is changed
+    if (mustChangePin) return
+
+    getOrInitFleetSettings(DEFAULT_FLEET).then(setFleet).catch(e => setError(errorMessage(e)))
+    fleetStatus().then(setFleetState).catch(e => setError(errorMessage(e)))
+    getOrInitAdminApiSettings(DEFAULT_ADMIN_API).then(setAdminApi).catch(e => setError(errorMessage(e)))
+  }, [mustChangePin])
+
+  async function handleLevel(log_level: LogLevel) {
+    const updated = { ...config, log_level }
+
+    try {
+      setConfig(updated)
+      await saveConfig(updated)
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  async function handleExport(exporter: () => Promise<string>) {
+    setExporting(true)
+    setError(null)
+    setResult(null)
+
+    try {
+      setResult(await exporter())
+    } catch (e) {
+      setError(errorMessage(e))
+    } finally {
+      setExporting(false)
+    }
+  }
+
+  async function handleSaveFleet() {
+    setError(null)
+
+    try {
+      await saveFleetSettings(fleet)
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  async function handleSaveAdminApi() {
+    setError(null)
+    setAdminApiSaved(false)
+
+    try {
+      await saveAdminApiSettings(adminApi)
+      setAdminApiSaved(true)
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  async function handleChangePin() {
+    setError(null)
+    setPinChanged(false)
+
+    try {
+      await changeAdminPin(currentPin, newPin)
+      setCurrentPin("")
+      setNewPin("")
+      setPinChanged(true)
+      setMustChangePin(false)
+    } catch (e) {
+      setError(errorMessage(e))
+    }
+  }
+
+  async function handleSync() {
+    setSyncing(true)
+    setError(null)
+
+    try {
+      setFleetState(await syncFleet())
+    } catch (e) {
+      setError(errorMessage(e))
+    } finally {
+      setSyncing(false)
+    }
+  }
+
+  return (
+    <motion.div
+      id="admin-system"
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+    >
+      <h1 className="heading">
+        Booth <div>System</div>
+      </h1>
+
+      <div className="system-card">
+        <div className="system-title">Log level</div>
+        <div className="system-subtitle">Applies the next time the booth starts</div>
+        <div className="level-options">
+          {LEVELS.map(level => (
+            <button
+              key={level}
+              className="level-btn"
+              data-selected={(config.log_level ?? "info") == level}
+              onClick={() => handleLevel(level)}
+            >
+              {level}
+            </button>
+          ))}
+        </div>
+      </div>
+
+      <div className="system-card">
+        <div className="system-title">Export to USB</div>
+        <div className="export-fields">
+          <label>
+            Last
+            <input
+              type="number"
+              min={1}
+              value={days}
+              onChange={e => setDays(Math.max(1, Number(e.target.value)))}
+            />
+            days
+          </label>
+          <input
+            className="destination-input"
+            placeholder="USB drive folder, e.g. /media/usb"
+            value={destination}
+            onChange={e => setDestination(e.target.value)}
+          />
+        </div>
+        <div className="export-actions">
+          <button
+            className="export-btn"
+            onClick={() => handleExport(() => exportLogs(days, destination))}
+            disabled={exporting || !destination}
+          >
+            Export logs
+          </button>
+          <button
+            className="export-btn"
+            onClick={() => handleExport(() => exportDiagnostics(destination))}
+            disabled={exporting || !destination}
+          >
+            Export diagnostics
+          </button>
+        </div>
+        {exporting && <div className="system-subtitle">Exporting...</div>}
+        {result && <div className="system-subtitle">Saved to {result}</div>}
+      </div>
+
+      <div className="system-card">
+        <div className="system-title">Admin PIN</div>
+        {mustChangePin && <div className="system-subtitle">The booth is still on the default PIN. Choose a new one to continue.</div>}
+        <div className="export-fields">
+          <input
+            type="password"
+            inputMode="numeric"
+            maxLength={4}
+            placeholder="Current PIN"
+            value={currentPin}
+            onChange={e => setCurrentPin(e.target.value.replace(/\D/g, ""))}
+          />
+          <input
+            type="password"
+            inputMode="numeric"
+            maxLength={4}
+            placeholder="New PIN"
+            value={newPin}
+            onChange={e => setNewPin(e.target.value.replace(/\D/g, ""))}
+          />
+          <button
+            className="export-btn"
+            onClick={handleChangePin}
+            disabled={currentPin.length != 4 || newPin.length != 4}
+          >
+            Change PIN
+          </button>
+        </div>
+        {pinChanged && <div className="system-subtitle">PIN changed</div>}
+      </div>
+
+      <div className="system-card">
+        <div className="system-title">Fleet sync</div>
+        <div className="system-subtitle">Pulls plans and layouts from the fleet server and reports sales</div>
+        <div className="export-fields">
+          <label>
+            <input
+              type="checkbox"
+              checked={fleet.enabled}
+              onChange={e => setFleet({ ...fleet, enabled: e.target.checked })}
+            />
+            Enabled
+          </label>
+          <label>
+            Every
+            <input
+              type="number"
+              min={30}
+              value={fleet.interval_secs}
+              onChange={e => setFleet({ ...fleet, interval_secs: Math.max(30, Number(e.target.value)) })}
+            />
+            seconds
+          </label>
+        </div>
+        <input
+          className="destination-input"
+          placeholder="Fleet server, e.g. https://fleet.example.com"
+          value={fleet.endpoint}
+          onChange={e => setFleet({ ...fleet, endpoint: e.target.value })}
+        />
+        <input
+          className="destination-input"
+          placeholder="Fleet public key (base64)"
+          value={fleet.public_key}
+          onChange={e => setFleet({ ...fleet, public_key: e.target.value })}
+        />
+        <input
+          className="destination-input"
+          placeholder="Booth token (optional)"
+          value={fleet.token ?? ""}
+          onChange={e => setFleet({ ...fleet, token: e.target.value || null })}
+        />
+        <div className="export-actions">
+          <button className="export-btn" onClick={handleSaveFleet}>
+            Save
+          </button>
+          <button
+            className="export-btn"
+            onClick={handleSync}
+            disabled={syncing || !fleet.enabled}
+          >
+            Sync now
+          </button>
+        </div>
+        {fleetState && (
+          <div className="system-subtitle">
+            Config version {fleetState.config_version}, last pulled {formatTime(fleetState.last_pull)}, last reported {formatTime(fleetState.last_push)}
+            {fleetState.last_error && <div>Last error: {fleetState.last_error}</div>}
+          </div>
+        )}
+      </div>
+
+      <div className="system-card">
+        <div className="system-title">Admin API</div>
+        <div className="system-subtitle">Lets attendants manage the booth from a phone on the same network. It is not encrypted, so only use it on a network just for staff. Applies the next time the booth starts</div>
+        <div className="export-fields">
+          <label>
+            <input
+              type="checkbox"
+              checked={adminApi.enabled}
+              onChange={e => setAdminApi({ ...adminApi, enabled: e.target.checked })}
+            />
+            Enabled
+          </label>
+          <label>
+            Address
+            <input
+              placeholder="The booth's IP on the staff network"
+              value={adminApi.address}
+              onChange={e => setAdminApi({ ...adminApi, address: e.target.value.trim() })}
+            />
+          </label>
+          <label>
+            Port
+            <input
+              type="number"
+              min={1024}
+              max={65535}
+              value={adminApi.port}
+              onChange={e => setAdminApi({ ...adminApi, port: Number(e.target.value) })}
+            />
+          </label>
+        </div>
+        <div className="export-fields">
+          <input
+            className="destination-input"
+            placeholder="Access token, at least 16 characters"
+            value={adminApi.token}
+            onChange={e => setAdminApi({ ...adminApi, token: e.target.value })}
+          />
+          <button
+            className="export-btn"
+            onClick={() => setAdminApi({ ...adminApi, token: crypto.randomUUID().replace(/-/g, "") })}
+          >
+            Generate
+          </button>
+        </div>
+        <div className="export-actions">
+          <button className="export-btn" onClick={handleSaveAdminApi}>
+            Save
+          </button>
+        </div>
+        {adminApiSaved && <div className="system-subtitle">Saved. Restart the booth to apply</div>}
+      </div>
+
+      {error && <div className="system-error">{error}</div>}
+    </motion.div>
+  )
+}
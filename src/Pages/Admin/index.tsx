@@ -3,12 +3,18 @@ import { Outlet, useNavigate } from 'react-router-dom'
 
 import AdminNav from '../../Components/AdminNav'
 import Header from '../../Components/Header'
+import { adminLogout } from '../../Services/commands'
 
 import './styles.css'
 
 export default function Admin() {
   const navigate = useNavigate()
 
+  function handleExit() {
+    adminLogout().catch(e => console.error(e))
+    navigate('/')
+  }
+
   return (
       <motion.div
         id='admin'
@@ -16,7 +22,7 @@ export default function Admin() {
         animate={{ opacity: 1 }}
         exit={{ opacity: 0 }}
       >
-        <Header backCallback={() => navigate('/')} />
+        <Header backCallback={handleExit} />
         <AdminNav />
         <div className="admin-container">
           <Outlet />
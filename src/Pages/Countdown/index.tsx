@@ -1,21 +1,61 @@
 import { useEffect, useState } from "react";
-import { useNavigate } from "react-router-dom";
+import { useLocation, useNavigate } from "react-router-dom";
 import { motion } from "framer-motion";
-import { path } from "@tauri-apps/api";
-import { invoke } from "@tauri-apps/api/core";
-import { pictureDir } from "@tauri-apps/api/path";
+import { listen } from "@tauri-apps/api/event";
 
 import { useData } from "../../Contexts/DataContext";
 
 import './styles.css'
 import { Layout } from "../../types";
+import { captureAnimation, captureSlot, startPreview, startSession, stopPreview } from "../../Services/commands";
+import errorMessage from "../../Utils/error";
+import selectedImages from "../../Utils/session";
+
+// Rough width/height of one photo cell per layout, to frame the guest
+const CROP_GUIDES: Record<Layout, number> = {
+  [Layout.A]: 1.4,
+  [Layout.B]: 1.4,
+  [Layout.C]: 1.3,
+};
 
 function Countdown() {
   const navigate = useNavigate();
+  const location = useLocation();
+  // Set when the guest came back from the preview to retake one photo, or
+  // every photo of the strip
+  const retake: number | "all" | undefined = location.state?.retake;
+  const retakes: number = location.state?.retakes ?? 0;
+  const first = typeof retake == "number" ? retake + 1 : 1;
+
   const [count, setCount] = useState(5);
-  const [photoIndex, setPhotoIndex] = useState(1)
+  const [photoIndex, setPhotoIndex] = useState(first)
   const [isStarting, setIsStarting] = useState(true)
-  const { options, setImages } = useData();
+  const [frame, setFrame] = useState<string>();
+  const [error, setError] = useState<string | null>(null);
+  const { options, config, setImages, setAnimation } = useData();
+
+  useEffect(() => {
+    if (retake === undefined) {
+      setAnimation(null)
+      setImages([])
+
+      startSession(options.layout!).catch(err => {
+        console.error("Failed to start the capture session:", err);
+        setError(errorMessage(err));
+      });
+    }
+
+    const unlisten = listen<{ image: string }>("preview-frame", (event) => {
+      setFrame(event.payload.image);
+    });
+
+    startPreview(true).catch(err => console.error("Failed to start preview:", err));
+
+    return () => {
+      unlisten.then(fn => fn());
+      stopPreview();
+    }
+  }, [])
 
   useEffect(() => {
     const startDelay = setTimeout(() => {
@@ -28,34 +68,52 @@ function Countdown() {
   useEffect(() => {
     if (isStarting) return
 
-    let photo_num = options.layout == Layout.A ? 2 : 4;
+    // Retaking one photo only takes that one again
+    let photo_num = typeof retake == "number" ? retake + 1 : options.layout == Layout.A ? 2 : 4;
 
     if (count === 0 && photoIndex <= photo_num) {
       async function capturePhoto() {
-        const pictures = await pictureDir();
         try {
-          let img_path = await path.join(pictures, `photo-${photoIndex}.jpg`)
-          let img = await invoke<string>("capture", { outputPath: img_path });
-          setImages(prev => [...prev, img]);
+          const session = await captureSlot(photoIndex - 1);
+          setImages(selectedImages(session));
+          return true;
         } catch (err) {
           console.error("Failed to capture image:", err);
+          setError(errorMessage(err));
+          return false;
         }
       }
 
-      capturePhoto().then(() => {
+      capturePhoto().then((captured) => {
+        if (photoIndex < photo_num || !captured) {
+          // Capturing takes the camera over from the preview
+          startPreview(true).catch(err => console.error("Failed to start preview:", err));
+        }
+
+        if (!captured) return;
+
         if (photoIndex < photo_num) {
           setTimeout(() => {
             setPhotoIndex(prev => prev + 1);
             setCount(5);
           }, 1000);
+        } else if (retake !== undefined) {
+          setTimeout(() => {
+            navigate("/preview", { replace: true, state: { retakes } });
+          }, 1000);
+        } else if (options.digital && config.animation) {
+          captureAnimation(config.animation)
+            .then(setAnimation)
+            .catch(err => console.error("Failed to capture animation:", err))
+            .finally(() => navigate("/mail"));
         } else {
           setTimeout(() => {
-            navigate(options.digital ? "/mail" : "/greeting");
+            navigate(options.digital ? "/mail" : "/preview");
           }, 1000);
         }
       });
     }
-  }, [count, photoIndex, navigate, options.digital, setImages, isStarting]);
+  }, [count, photoIndex, navigate, options.digital, config.animation, setImages, setAnimation, isStarting, retake, retakes]);
 
   useEffect(() => {
     if (isStarting || count <= 0) return
@@ -71,6 +129,13 @@ function Countdown() {
 
   return (
     <div id="countdown">
+      {frame && <div className="preview">
+        <img src={frame} className="preview-frame" />
+        {options.layout && <div
+          className="preview-guide"
+          style={{ aspectRatio: CROP_GUIDES[options.layout] }}
+        />}
+      </div>}
       {isStarting ? (
         <motion.span
           key="starting"
@@ -96,9 +161,13 @@ function Countdown() {
           </motion.span>
         )
       )}
-      {!isStarting && <span className="count-text">
+      {!isStarting && !error && <span className="count-text">
         Choose a pose now, stay still after 1...
       </span>}
+      {error && <div className="count-error">
+        <span>{error}</span>
+        <button onClick={() => { setError(null); setCount(5); }}>Try again</button>
+      </div>}
     </div>
   );
 }
@@ -0,0 +1,84 @@
+import { motion } from 'framer-motion'
+import { useState } from 'react'
+import { useNavigate } from 'react-router-dom'
+
+import Numpad from '../../../Components/Numpad'
+import Header from '../../../Components/Header'
+
+import { useData } from '../../../Contexts/DataContext'
+import { recordCashPayment } from '../../../Services/commands'
+import calculate from '../../../Utils/calculate'
+import errorMessage from '../../../Utils/error'
+import { Mode } from '../../../types'
+
+import '../../Passcode/styles.css'
+import './styles.css'
+
+export default function Cash() {
+  const navigate = useNavigate()
+  const [status, setStatus] = useState<number>(-1)
+  const [code, setCode] = useState<Array<string>>(["", "", "", ""])
+  const [error, setError] = useState<string | null>(null)
+
+  const { options, config, mode } = useData()
+  const amount = calculate(options, mode, config)
+
+  async function handleEnter(pin: string) {
+    try {
+      // The backend prices the sale itself; `amount` is only shown
+      await recordCashPayment(options.copies!, options.digital && mode == Mode.AUTOMATIC, pin)
+      setError(null)
+      setStatus(1)
+      setTimeout(() => {
+        navigate('/countdown')
+      }, 1000);
+    } catch (e) {
+      setError(errorMessage(e))
+      setStatus(0)
+      setTimeout(() => {
+        setStatus(-1)
+        setCode(["", "", "", ""])
+      }, 1000);
+    }
+  }
+
+  return (
+    <motion.div
+      id='passcode'
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+    >
+      <Header backCallback={() => navigate(-1)}/>
+      <div className="cash-heading">
+        <div className="cash-title">Pay ₹{amount / 100} in cash</div>
+        <div className="cash-subtitle">{error ?? "Ask an attendant to enter their PIN"}</div>
+      </div>
+      <div className="passcode-container">
+        <div className="numpad-container">
+          <Numpad
+            code={code}
+            setCode={setCode}
+            onEnter={handleEnter}
+          />
+        </div>
+        <div className="input-container">
+          <div
+            className="input-box"
+            data-status={status == 0 ? 'incorrect' : status == 1 ? 'correct' : ''}
+          >
+            {code.map((digit, index) => (
+              <div
+                key={index}
+                className="input"
+                data-filled={digit !== ""}
+              >
+                {digit === "" ? "﹡": "∗"}
+              </div>
+            ))}
+          </div>
+        </div>
+      </div>
+    </motion.div>
+  )
+}
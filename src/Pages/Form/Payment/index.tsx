@@ -16,14 +16,20 @@ import './styles.css'
 export default function Payment() {
   const navigate = useNavigate()
   const [time, setTime] = useState<number>(0)
-  const { qrCode, loading, fetchQrCode, paid, pollingIntervalRef } = usePayment()
+  const { qrCode, loading, error, notReady, fetchQrCode, paid, pollingIntervalRef } = usePayment()
 
   const dev = false;
 
   const { setOptions, setImages, options, config, mode } = useData()
+  const cash = !!config.attendants?.length
+
+  function payCash() {
+    if (pollingIntervalRef.current) clearInterval(pollingIntervalRef.current)
+    navigate('/cash')
+  }
   
   useEffect(() => {
-    fetchQrCode(calculate(options, mode, config))
+    fetchQrCode(calculate(options, mode, config), options.copies ?? 1)
   }, [fetchQrCode])
   
   useEffect(() => {
@@ -93,12 +99,26 @@ export default function Payment() {
             />
           </div>
         )}
+        {!loading && error && (
+          <div className='payment-container'>
+            <div className="payment-heading">
+              <div className="payment-title">Sorry, we can't take payments right now</div>
+              <div className="payment-subtitle">{error}</div>
+              {cash && !notReady && (
+                <button className="cash-btn" onClick={payCash}>Pay with cash instead</button>
+              )}
+            </div>
+          </div>
+        )}
         {
-          !loading && (
+          !loading && !error && (
             <div className='payment-container'>
               <div className="payment-heading">
                 <div className="payment-title">Scan the QR to make payment</div>
                 <div className="payment-subtitle">QR will expire in {time}s</div>
+                {cash && (
+                  <button className="cash-btn" onClick={payCash}>Pay with cash</button>
+                )}
               </div>
               <div className="qr-container">
                 <div className="qr-title">Payment</div>
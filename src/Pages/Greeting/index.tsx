@@ -1,21 +1,23 @@
 import { motion } from 'framer-motion'
 import { useEffect, useMemo, useState } from 'react'
-import { useNavigate } from 'react-router-dom'
+import { useLocation, useNavigate } from 'react-router-dom'
 import { invoke } from '@tauri-apps/api/core'
 import { pictureDir } from '@tauri-apps/api/path'
 
 import { useData } from '../../Contexts/DataContext'
+import { abandonPrint, getOrInitPages, printRendered } from '../../Services/commands'
 import reset from '../../Utils/reset'
 
 import './styles.css'
 import { path } from '@tauri-apps/api'
 import { DotLottieReact } from '@lottiefiles/dotlottie-react'
-import { savePages } from '../../Services/commands'
-import { Print } from '../../types'
+import { CommandError, Print } from '../../types'
 
 export default function Greeting() {
-  const { setOptions, options, images, setImages, pages, setPages } = useData()
+  const { setOptions, options, images, setImages, setPages } = useData()
   const navigate = useNavigate()
+  // Set when the guest approved a preview, which is printed as rendered
+  const previewId: string | undefined = useLocation().state?.previewId
 
   const greetings = useMemo(() => [
     "Photos so good, they might break the internet!",
@@ -31,6 +33,10 @@ export default function Greeting() {
   )
   const [progressText, setProgressText] = useState("0 of 0")
   const [showLoader, setShowLoader] = useState(true)
+  const [printError, setPrintError] = useState<string | null>(null)
+  const [attempt, setAttempt] = useState(1)
+  // The backend refunds the sale once no prints are left, so retrying stops
+  const [canRetry, setCanRetry] = useState(true)
 
   const stripCount = options.copies || 1
 
@@ -39,25 +45,33 @@ export default function Greeting() {
 
     const printPhotos = async () => {
       try {
-        let pictures = await pictureDir()
-        let img_path = await path.join(pictures, "print-strip.png")
-        await invoke("print", {
-          images: images,
-          outputPath: img_path,
-          colorMode: options.print == Print.COLOR ? "COLOR" : "B&W",
-          copies: options.copies,
-          layout: options.layout
-        })
+        if (previewId) {
+          await printRendered(previewId, options.copies!)
+        } else {
+          let pictures = await pictureDir()
+          let img_path = await path.join(pictures, "print-strip.png")
+          await invoke("print", {
+            images: images,
+            outputPath: img_path,
+            colorMode: options.print == Print.COLOR ? "COLOR" : "B&W",
+            copies: options.copies,
+            layout: options.layout
+          })
+        }
         
-        const updatedPages = pages + options.copies!
-        const finalPages = updatedPages > 699 ? 0 : updatedPages
-
-        await savePages(finalPages)
-        setPages(finalPages)
+        // The backend counts the pages against the printer's pack size
+        setPages(await getOrInitPages())
 
         console.log("Print successful")
       } catch (err) {
-        console.error("Error during the printing:", err)
+        const error = err as CommandError
+        console.error("Error during the printing:", error.details ?? error)
+        setPrintError(error.message ?? "Your photos could not be printed")
+        setCanRetry((error.attempts_left ?? 1) > 0)
+
+        // Stay on this page until the guest retries or gives up
+        timers.forEach(clearTimeout)
+        setShowLoader(false)
       }
     }
 
@@ -96,7 +110,18 @@ export default function Greeting() {
     })
 
     return () => timers.forEach(clearTimeout)
-  }, [])
+  }, [attempt])
+
+  function handleRetry() {
+    setPrintError(null)
+    setAttempt(attempt + 1)
+  }
+
+  function handleGiveUp() {
+    abandonPrint()
+      .catch(err => console.error("Failed to give up on the print:", err))
+      .finally(() => reset(setOptions, setImages, navigate))
+  }
 
   return (
     <motion.div
@@ -110,8 +135,14 @@ export default function Greeting() {
           {greetingText}
         </div>
         <div className="greeting-subtitle">
-          Collect your prints outside
+          {printError ? `${printError}. Please ask an attendant for help` : "Collect your prints outside"}
         </div>
+        {printError && (
+          <div className="greeting-actions">
+            {canRetry && <button className="greeting-btn" onClick={handleRetry}>Try again</button>}
+            <button className="greeting-btn" onClick={handleGiveUp}>Cancel</button>
+          </div>
+        )}
         <div className="greeting-progress">
           {progressText}
           {showLoader && <DotLottieReact
@@ -21,7 +21,7 @@ export default function Mail() {
   const keyboardRef = useRef(null)
   const inputRef = useRef<HTMLInputElement | null>(null)
 
-  const { images, options } = useData()
+  const { images, animation, options } = useData()
 
   useEffect(() => {
     async function fetchPath() {
@@ -38,12 +38,13 @@ export default function Mail() {
         documentPath: documentPath,
         userEmail: email,
         photoPaths: images,
-        layout: options.layout
+        layout: options.layout,
+        animation
       })
     } catch (err) {
       console.error("Error storing email:", err)
     } finally {
-      navigate("/greeting")
+      navigate("/preview")
     }
   }
 
@@ -6,14 +6,35 @@ import Header from '../../Components/Header';
 
 import { useNavigate } from 'react-router-dom';
 
+import { adminLogin } from '../../Services/commands';
+import errorMessage from '../../Utils/error';
+
 import './styles.css';
 
 export default function Passcode() {
   const navigate = useNavigate()
   const [status, setStatus] = useState<number>(-1)
   const [code, setCode] = useState<Array<string>>(["", "", "", ""])
+  const [error, setError] = useState<string | null>(null)
 
-  const correct = "5412";
+  async function handleEnter(pin: string) {
+    try {
+      const mustChangePin = await adminLogin(pin)
+      setError(null)
+      setStatus(1)
+      setTimeout(() => {
+        if (mustChangePin) navigate("/admin/system", { state: { mustChangePin } })
+        else navigate("/admin/mode")
+      }, 1000);
+    } catch (e) {
+      setError(errorMessage(e))
+      setStatus(0)
+      setTimeout(() => {
+        setStatus(-1)
+        setCode(["", "", "", ""])
+      }, 1000);
+    }
+  }
 
   return (
     <motion.div
@@ -28,8 +49,7 @@ export default function Passcode() {
           <Numpad
             code={code}
             setCode={setCode}
-            setStatus={setStatus}
-            correct={correct}
+            onEnter={handleEnter}
           />
         </div>
         <div className="input-container">
@@ -47,6 +67,7 @@ export default function Passcode() {
               </div>
             ))}
           </div>
+          {error && <div className="passcode-error">{error}</div>}
         </div>
       </div>
     </motion.div>
@@ -0,0 +1,156 @@
+import { motion } from 'framer-motion'
+import { useEffect, useState } from 'react'
+import { useLocation, useNavigate } from 'react-router-dom'
+import { DotLottieReact } from '@lottiefiles/dotlottie-react'
+
+import { useData } from '../../Contexts/DataContext'
+import { getSession, renderPreview, selectTake } from '../../Services/commands'
+import { CaptureSession, Print } from '../../types'
+import selectedImages from '../../Utils/session'
+
+import './styles.css'
+
+// The strip prints by itself if the guest walks away from the preview
+const APPROVE_AFTER = 30
+// Photos a guest may retake, so one session can't hold up the queue
+const MAX_RETAKES = 3
+
+export default function Preview() {
+  const { options, images, setImages } = useData()
+  const navigate = useNavigate()
+  // Retakes already used, carried through the countdown and back
+  const retakes: number = useLocation().state?.retakes ?? 0
+
+  const [session, setSession] = useState<CaptureSession | null>(null)
+  const [previewId, setPreviewId] = useState<string | null>(null)
+  const [image, setImage] = useState<string | null>(null)
+  const [count, setCount] = useState(APPROVE_AFTER)
+  const [choosing, setChoosing] = useState(false)
+
+  useEffect(() => {
+    getSession()
+      .catch(err => {
+        console.error("Failed to load the capture session:", err)
+        return null
+      })
+      .then(current => {
+        setSession(current)
+        render(current)
+      })
+  }, [])
+
+  useEffect(() => {
+    // The guest is still choosing photos, so nothing prints behind their back
+    if (!previewId || choosing) return
+
+    if (count <= 0) {
+      handlePrint()
+      return
+    }
+
+    const timer = setTimeout(() => setCount(count - 1), 1000)
+
+    return () => clearTimeout(timer)
+  }, [previewId, count, choosing])
+
+  async function render(current: CaptureSession | null) {
+    const colorMode = options.print == Print.COLOR ? "COLOR" : "B&W"
+
+    setPreviewId(null)
+    setImage(null)
+
+    try {
+      const preview = await renderPreview(
+        current ? selectedImages(current) : images,
+        colorMode,
+        options.layout!,
+        current?.id
+      )
+
+      setPreviewId(preview.id)
+      setImage(preview.image)
+      setCount(APPROVE_AFTER)
+    } catch (err) {
+      // Greeting renders and prints the strip itself, reporting any error
+      console.error("Failed to render the preview:", err)
+      navigate("/greeting", { replace: true })
+    }
+  }
+
+  function handlePrint() {
+    navigate("/greeting", { replace: true, state: { previewId } })
+  }
+
+  function handleRetake(slot: number | "all") {
+    navigate("/countdown", { replace: true, state: { retake: slot, retakes: retakes + 1 } })
+  }
+
+  async function handleTake(slot: number) {
+    const { takes, selected } = session!.slots[slot]
+
+    try {
+      const updated = await selectTake(slot, ((selected ?? 0) + 1) % takes.length)
+
+      setSession(updated)
+      setImages(selectedImages(updated))
+      await render(updated)
+    } catch (err) {
+      console.error("Failed to switch photos:", err)
+    }
+  }
+
+  return (
+    <motion.div
+      id='preview'
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      exit={{ opacity: 0 }}
+    >
+      <div className='preview-container'>
+        <h1 className="heading">Here is your <div>strip</div></h1>
+        {image ? (
+          <>
+            <img className="preview-image" src={image} alt="Your photo strip" />
+            {choosing && session ? (
+              <div className="preview-slots">
+                {session.slots.map((slot, i) => (
+                  <div key={i} className="preview-slot">
+                    Photo {i + 1}
+                    {retakes < MAX_RETAKES && (
+                      <button className="preview-btn" onClick={() => handleRetake(i)}>Retake</button>
+                    )}
+                    {slot.takes.length > 1 && (
+                      <button className="preview-btn" onClick={() => handleTake(i)}>
+                        Shot {(slot.selected ?? 0) + 1} of {slot.takes.length}
+                      </button>
+                    )}
+                  </div>
+                ))}
+                {retakes < MAX_RETAKES && (
+                  <button className="preview-btn" onClick={() => handleRetake("all")}>Retake all</button>
+                )}
+                <button className="preview-btn" onClick={() => setChoosing(false)}>Done</button>
+              </div>
+            ) : (
+              <div className="preview-actions">
+                {session && (
+                  <button className="preview-btn" onClick={() => setChoosing(true)}>Change photos</button>
+                )}
+                <button className="preview-btn" onClick={handlePrint}>
+                  Print ({count})
+                </button>
+              </div>
+            )}
+          </>
+        ) : (
+          <DotLottieReact
+            className='preview-loader'
+            src='/loader.lottie'
+            loop
+            autoplay
+          />
+        )}
+      </div>
+    </motion.div>
+  )
+}
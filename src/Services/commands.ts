@@ -1,12 +1,33 @@
 import { documentDir } from "@tauri-apps/api/path";
 import { Config } from "../Contexts/DataContext";
 import { invoke } from "@tauri-apps/api/core";
-import { LayoutData } from "../types";
+import { AdminApiSettings, AdminLogin, AnimationMode, Calibration, CameraInfo, CaptureSession, FleetSettings, FleetState, Layout, LayoutData, Preview, Printers, PrinterStatus, Readiness, Sale, SalesReport, SelfTestReport } from "../types";
+
+// Issued by adminLogin and sent with every admin-only command
+let adminToken = ""
+
+// Resolves to whether the booth is still on the default PIN, which has to be
+// changed before anything else
+export async function adminLogin(pin: string) {
+    const login = await invoke<AdminLogin>("admin_login", { pin })
+    adminToken = login.token
+
+    return login.must_change_pin
+}
+
+export async function adminLogout() {
+    await invoke("admin_logout", { token: adminToken })
+    adminToken = ""
+}
+
+export async function changeAdminPin(current: string, pin: string) {
+    await invoke("change_admin_pin", { token: adminToken, current, pin })
+}
 
 export async function saveConfig(config: Config) {
     const dir = await documentDir()
 
-    await invoke("save_config", { directory: dir, config })
+    await invoke("save_config", { directory: dir, config, token: adminToken })
 }
 export async function getOrInitConfig(defaults: Config) {
     const dir = await documentDir()
@@ -17,7 +38,7 @@ export async function getOrInitConfig(defaults: Config) {
 export async function saveLayouts(layouts: LayoutData[]) {
     const dir = await documentDir()
 
-    await invoke("save_layouts", { directory: dir, layouts })
+    await invoke("save_layouts", { directory: dir, layouts, token: adminToken })
 }
 
 export async function getOrInitLayouts(defaults: LayoutData[]) {
@@ -29,11 +50,158 @@ export async function getOrInitLayouts(defaults: LayoutData[]) {
 export async function savePages(pages: number) {
     const dir = await documentDir()
 
-    await invoke("save_pages", { directory: dir, pages })
+    await invoke("save_pages", { directory: dir, pages, token: adminToken })
 }
 
 export async function getOrInitPages() {
     const dir = await documentDir()
 
     return await invoke<number>("get_or_init_pages", { directory: dir, default: 0 });
-}
\ No newline at end of file
+}
+
+export async function savePrinters(printers: Printers) {
+    const dir = await documentDir()
+
+    await invoke("save_printers", { directory: dir, printers, token: adminToken })
+}
+
+export async function getOrInitPrinters(defaults: Printers) {
+    const dir = await documentDir()
+
+    return await invoke<Printers>("get_or_init_printers", { directory: dir, defaults });
+}
+
+// With a sessionId the selected takes of that capture session are rendered
+// instead of images, and the session ends once the preview is printed.
+// branding replaces the text under the photos
+export async function renderPreview(images: string[], colorMode: string, layout: Layout, sessionId?: string, branding?: string) {
+    return await invoke<Preview>("render_preview", { images, colorMode, layout, sessionId, branding })
+}
+
+export async function printRendered(previewId: string, copies: number) {
+    await invoke("print_rendered", { previewId, copies })
+}
+
+export async function startSession(layout: Layout) {
+    return await invoke<CaptureSession>("start_session", { layout })
+}
+
+export async function captureSlot(slot: number) {
+    return await invoke<CaptureSession>("capture_slot", { slot })
+}
+
+export async function selectTake(slot: number, take: number) {
+    return await invoke<CaptureSession>("select_take", { slot, take })
+}
+
+export async function getSession() {
+    return await invoke<CaptureSession | null>("get_session")
+}
+
+export async function endSession() {
+    await invoke("end_session")
+}
+
+export async function startPreview(mirror: boolean) {
+    await invoke("start_preview", { mirror })
+}
+
+export async function stopPreview() {
+    await invoke("stop_preview")
+}
+
+export async function captureAnimation(mode: AnimationMode) {
+    return await invoke<string>("capture_animation", { mode })
+}
+
+export async function listCameras() {
+    return await invoke<CameraInfo[]>("list_cameras")
+}
+
+export async function cameraSelfTest() {
+    return await invoke<SelfTestReport>("camera_self_test")
+}
+
+export async function calibrateExposure() {
+    return await invoke<Calibration>("calibrate_exposure", { token: adminToken })
+}
+
+export async function printerStatus() {
+    return await invoke<PrinterStatus[]>("printer_status")
+}
+
+export async function printTestPage(layout: Layout) {
+    await invoke("print_test_page", { layout, token: adminToken })
+}
+
+export async function boothReadiness(copies: number) {
+    return await invoke<Readiness>("booth_readiness", { copies })
+}
+
+export async function getLedger() {
+    return await invoke<Sale[]>("get_ledger")
+}
+
+export async function refundSale(id: string, reason: string) {
+    return await invoke<Sale>("refund_sale", { id, reason, token: adminToken })
+}
+
+export async function unfulfilledSales() {
+    return await invoke<Sale[]>("unfulfilled_sales")
+}
+
+export async function fulfilSale(id: string) {
+    return await invoke<Sale>("fulfil_sale", { id, token: adminToken })
+}
+
+export async function salesReport() {
+    return await invoke<SalesReport>("sales_report")
+}
+
+export async function abandonPrint() {
+    await invoke("abandon_print")
+}
+
+export async function recordCashPayment(copies: number, digital: boolean, pin: string) {
+    return await invoke<Sale>("record_cash_payment", { copies, digital, pin })
+}
+
+export async function exportLogs(days: number, destination: string) {
+    return await invoke<string>("export_logs", { days, destination, token: adminToken })
+}
+
+export async function exportDiagnostics(destination: string) {
+    return await invoke<string>("export_diagnostics", { destination, token: adminToken })
+}
+
+export async function saveFleetSettings(settings: FleetSettings) {
+    const dir = await documentDir()
+
+    await invoke("save_fleet_settings", { directory: dir, settings, token: adminToken })
+}
+
+export async function getOrInitFleetSettings(defaults: FleetSettings) {
+    const dir = await documentDir()
+
+    return await invoke<FleetSettings>("get_or_init_fleet_settings", { directory: dir, defaults, token: adminToken });
+}
+
+export async function fleetStatus() {
+    return await invoke<FleetState>("fleet_status")
+}
+
+export async function syncFleet() {
+    return await invoke<FleetState>("sync_fleet", { token: adminToken })
+}
+
+export async function saveAdminApiSettings(settings: AdminApiSettings) {
+    const dir = await documentDir()
+
+    await invoke("save_admin_api_settings", { directory: dir, settings, token: adminToken })
+}
+
+export async function getOrInitAdminApiSettings(defaults: AdminApiSettings) {
+    const dir = await documentDir()
+
+    return await invoke<AdminApiSettings>("get_or_init_admin_api_settings", { directory: dir, defaults, token: adminToken });
+}
@@ -7,7 +7,7 @@ export default function calculate(
     config: Config
 ): number {
     let price = config.plans.find(_ => _.copies == options.copies)?.price ?? 0
-    let digitalPrice = options.digital && mode == Mode.AUTOMATIC ? 99 : 0
+    let digitalPrice = options.digital && mode == Mode.AUTOMATIC ? config.digital.price : 0
 
     price += digitalPrice
 
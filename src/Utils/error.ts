@@ -0,0 +1,9 @@
+import { CommandError } from "../types";
+
+export default function errorMessage(e: unknown): string {
+    if (typeof e == "string") return e
+
+    const err = e as CommandError
+    if (typeof err?.details == "string") return err.details
+    return err?.message ?? String(e)
+}
@@ -0,0 +1,6 @@
+import { CaptureSession } from "../types";
+
+// The take the guest kept of every photo, in order
+export default function selectedImages(session: CaptureSession): string[] {
+    return session.slots.flatMap(slot => slot.selected == null ? [] : [slot.takes[slot.selected]])
+}
@@ -10,6 +10,8 @@ export enum Print {
 
 export enum Layout { A = "A", B = "B", C = "C" }
 
+export enum AnimationMode { Gif = "Gif", Boomerang = "Boomerang" }
+
 export interface Plan {
   title: string
   price: number
@@ -35,4 +37,195 @@ export interface Options {
   copies: number | null,
   digital: boolean,
   print: Print | null
-}
\ No newline at end of file
+}
+export type RenderingIntent = "Perceptual" | "RelativeColorimetric" | "Saturation" | "AbsoluteColorimetric"
+
+export interface PrinterProfile {
+  name: string,
+  queue: string | null,
+  paper_width: number,
+  paper_height: number,
+  dpi: number,
+  margin: number,
+  bleed: number,
+  media: string,
+  cuts_strips: boolean,
+  strip_media: string | null,
+  icc_profile?: string | null,
+  rendering_intent?: RenderingIntent,
+  // Prints per paper pack; the page counter wraps back to 0 after this many
+  paper_capacity?: number
+}
+
+export interface Printers {
+  active: string,
+  profiles: PrinterProfile[]
+}
+
+export interface Preview {
+  id: string,
+  image: string
+}
+
+export interface Slot {
+  takes: string[],
+  selected: number | null
+}
+
+export interface CaptureSession {
+  id: string,
+  layout: Layout,
+  slots: Slot[]
+}
+
+export interface CameraMode {
+  width: number,
+  height: number,
+  fps: number
+}
+
+export interface CameraInfo {
+  index: number,
+  model: string,
+  width: number,
+  height: number,
+  format: string,
+  device: string,
+  modes: CameraMode[]
+}
+
+export interface SelfTestReport {
+  camera: CameraInfo | null,
+  width: number,
+  height: number,
+  mean_brightness: number,
+  clipped_shadows: number,
+  clipped_highlights: number,
+  capture_ms: number
+}
+
+export interface CaptureSettings {
+  shutter: number,
+  gain: number,
+  ev: number
+}
+
+export interface ExposureStats {
+  mean_brightness: number,
+  clipped_shadows: number,
+  clipped_highlights: number
+}
+
+export interface Calibration {
+  settings: CaptureSettings,
+  exposure: ExposureStats,
+  iterations: number,
+  converged: boolean
+}
+
+export interface Marker {
+  name: string,
+  kind: string,
+  level: number | null
+}
+
+export interface PrinterStatus {
+  profile: string,
+  queue: string | null,
+  online: boolean,
+  state: string,
+  accepting_jobs: boolean,
+  queued_jobs: number,
+  state_reasons: string[],
+  markers: Marker[]
+}
+
+export type ReadinessProblem =
+  | { reason: "camera_unavailable", detail: string }
+  | { reason: "printer_offline", printer: string, detail: string }
+  | { reason: "out_of_paper", remaining: number, needed: number }
+
+export interface Readiness {
+  ready: boolean,
+  problems: ReadinessProblem[]
+}
+
+export type CommandError = (
+  | { code: "booth_not_ready", message: string, details: ReadinessProblem[] }
+  | {
+      code: "image_missing" | "imaging" | "printer_offline" | "print" | "mail" | "payment" | "storage" | "unauthorized"
+        | "camera_busy" | "camera_not_detected" | "camera_timeout" | "camera" | "invalid" | "not_found" | "internal",
+      message: string,
+      details?: string
+    }
+) & {
+  // Set when a paid print failed: the prints the guest may still try
+  attempts_left?: number
+}
+
+export type PaymentMethod = "upi" | "cash"
+
+export type SaleStatus = "pending" | "expired" | "paid" | "fulfilled" | "refunding" | "refunded" | "refund_failed"
+
+export interface Sale {
+  id: string,
+  method: PaymentMethod,
+  amount: number,
+  status: SaleStatus,
+  created_at: number,
+  updated_at: number,
+  payment_id: string | null,
+  refund_id: string | null,
+  refund_attempts?: number,
+  note: string | null,
+  attendant: string | null,
+  revision: number
+}
+
+export interface Revenue {
+  sales: number,
+  amount: number
+}
+
+export interface SalesReport {
+  upi: Revenue,
+  cash: Revenue,
+  attendants: Record<string, Revenue>,
+  refunded: Revenue
+}
+
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace"
+
+export interface Attendant {
+  id: string,
+  // Only ever sent, to set a new PIN; the booth never returns it
+  pin?: string
+}
+
+export interface FleetSettings {
+  enabled: boolean,
+  endpoint: string,
+  public_key: string,
+  token: string | null,
+  interval_secs: number
+}
+
+export interface FleetState {
+  config_version: number,
+  last_pull: number | null,
+  last_push: number | null,
+  sales_synced_revision: number | null,
+  last_error: string | null
+}
+
+export interface AdminLogin {
+  token: string,
+  must_change_pin: boolean
+}
+
+export interface AdminApiSettings {
+  enabled: boolean,
+  address: string,
+  port: number,
+  token: string
+}